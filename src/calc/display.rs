@@ -14,6 +14,16 @@ pub fn display(data: HashMap<String, u64>) {
     }
 }
 
+pub fn display_breakdown(detail_title: &str, data: HashMap<(String, String), u64>) {
+    let output_rows = format_breakdown(data);
+
+    if output_rows.is_empty() {
+        println!("{}", NO_DATA_WARNING);
+    } else {
+        print_breakdown_table(detail_title, output_rows);
+    }
+}
+
 fn format(data: HashMap<String, u64>) -> Vec<(String, String)> {
     let mut output_rows = vec![];
 
@@ -28,6 +38,21 @@ fn format(data: HashMap<String, u64>) -> Vec<(String, String)> {
     output_rows
 }
 
+fn format_breakdown(data: HashMap<(String, String), u64>) -> Vec<(String, String, String)> {
+    let mut output_rows = vec![];
+
+    for ((project, detail), time_in_seconds) in data {
+        if time_in_seconds > 0 {
+            output_rows.push((project, detail, to_hms(time_in_seconds)));
+        }
+    }
+
+    // alphabetize the output by project name, then by detail
+    output_rows.sort_by(|(a, a_detail, _), (b, b_detail, _)| (a, a_detail).cmp(&(b, b_detail)));
+
+    output_rows
+}
+
 fn print_table(output_rows: Vec<(String, String)>) {
     let mut table = Table::new();
 
@@ -40,8 +65,20 @@ fn print_table(output_rows: Vec<(String, String)>) {
     table.printstd();
 }
 
+fn print_breakdown_table(detail_title: &str, output_rows: Vec<(String, String, String)>) {
+    let mut table = Table::new();
+
+    // header row is bold
+    table.add_row(row![b -> "Project Name", b -> detail_title, b -> "Time"]);
+    for (project, detail, time) in output_rows {
+        table.add_row(row![project, detail, time]);
+    }
+
+    table.printstd();
+}
+
 /// Converts a duration in seconds to a human readable string
-fn to_hms(seconds: u64) -> String {
+pub fn to_hms(seconds: u64) -> String {
     let hours = seconds / (60 * 60);
     let minutes = (seconds - (hours * 60 * 60)) / 60;
     let seconds = seconds - (hours * 60 * 60) - (minutes * 60);
@@ -107,4 +144,36 @@ mod tests {
     fn to_hms_hours() {
         assert_eq!("5 hours 10 minutes", to_hms((5 * 60 * 60) + (10 * 60) + 30));
     }
+
+    #[test]
+    fn format_breakdown_sorted() {
+        let mut data = HashMap::new();
+        data.insert((String::from("proj2"), String::from("Rust")), 60);
+        data.insert((String::from("proj1"), String::from("Rust")), 60);
+        data.insert((String::from("proj1"), String::from("Docs")), 30);
+        data.insert((String::from("proj1"), String::from("CI")), 0);
+
+        let output_rows = format_breakdown(data);
+
+        assert_eq!(
+            vec![
+                (
+                    String::from("proj1"),
+                    String::from("Docs"),
+                    String::from("30 seconds")
+                ),
+                (
+                    String::from("proj1"),
+                    String::from("Rust"),
+                    String::from("1 minute")
+                ),
+                (
+                    String::from("proj2"),
+                    String::from("Rust"),
+                    String::from("1 minute")
+                ),
+            ],
+            output_rows
+        );
+    }
 }
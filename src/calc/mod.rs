@@ -5,20 +5,70 @@ use std::fs::OpenOptions;
 use std::io::Read;
 use std::io::Write;
 
-mod raw_log;
+pub mod raw_log;
 use self::raw_log::raw_logs_from;
 
 mod span;
 use self::span::{get_spans_from, Span};
 
 mod display;
-use self::display::display;
+pub(crate) use self::display::to_hms;
+use self::display::{display, display_breakdown};
 use self::span::get_last_timestamp_per_project;
 use crate::calc::span::get_vec_raw_logs_from_map_last_timestamp_per_project;
 use crate::calc::span::spans_from;
 
+/// The detail used to break down the time spent on each project
+pub enum Breakdown {
+    Language,
+    Dir,
+    Tag,
+}
+
+impl Breakdown {
+    fn title(&self) -> &'static str {
+        match self {
+            Breakdown::Language => "Language",
+            Breakdown::Dir => "Directory",
+            Breakdown::Tag => "Tag",
+        }
+    }
+
+    fn detail_of(&self, span: &Span) -> Option<String> {
+        match self {
+            Breakdown::Language => span.language.clone(),
+            Breakdown::Dir => span.dir.clone(),
+            // most activity has no tag, which is known rather than missing
+            Breakdown::Tag => Some(span.tag.clone().unwrap_or_else(|| String::from("Untagged"))),
+        }
+    }
+}
+
 impl<'a> TimeTracker<'a> {
     pub fn calc(&self) -> Result<(), TimeTrackerError> {
+        let all_spans = self.process_raw_data()?;
+
+        display(calculate_project_total_time(all_spans));
+        Ok(())
+    }
+
+    /// Displays how the time spent on each project (or only the given project) is split
+    pub fn report(
+        &self,
+        breakdown: &Breakdown,
+        project: Option<&str>,
+    ) -> Result<(), TimeTrackerError> {
+        let all_spans = self.process_raw_data()?;
+
+        display_breakdown(
+            breakdown.title(),
+            calculate_project_breakdown_time(all_spans, breakdown, project),
+        );
+        Ok(())
+    }
+
+    /// Moves new raw data into the processed data file, and returns all processed spans
+    fn process_raw_data(&self) -> Result<Vec<Span>, TimeTrackerError> {
         // process raw data into spans
         let mut raw_data = String::new();
         {
@@ -80,10 +130,7 @@ impl<'a> TimeTracker<'a> {
         processed_data_file
             .read_to_string(&mut all_spans_string)
             .expect("Failed to read processed data");
-        let all_spans = spans_from(&all_spans_string)?;
-
-        display(calculate_project_total_time(all_spans));
-        Ok(())
+        spans_from(&all_spans_string)
     }
 }
 
@@ -101,6 +148,31 @@ fn calculate_project_total_time(spans: Vec<Span>) -> HashMap<String, u64> {
     project_totals
 }
 
+fn calculate_project_breakdown_time(
+    spans: Vec<Span>,
+    breakdown: &Breakdown,
+    project: Option<&str>,
+) -> HashMap<(String, String), u64> {
+    let mut breakdown_totals = HashMap::new();
+
+    for span in spans {
+        if project.is_some_and(|project| project != span.name) {
+            continue;
+        }
+
+        let span_duration = span.duration();
+        // time recorded without the detail (for example, before it was tracked) is shown as unknown
+        let detail = breakdown
+            .detail_of(&span)
+            .unwrap_or_else(|| String::from("Unknown"));
+
+        let duration = breakdown_totals.entry((span.name, detail)).or_insert(0);
+        *duration += span_duration;
+    }
+
+    breakdown_totals
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -113,11 +185,17 @@ mod tests {
             name: String::from("proj1"),
             start: 1,
             end: 5,
+            language: None,
+            dir: None,
+            tag: None,
         });
         spans.push(Span {
             name: String::from("proj1"),
             start: 11,
             end: 26,
+            language: None,
+            dir: None,
+            tag: None,
         });
 
         let project_totals = calculate_project_total_time(spans);
@@ -135,16 +213,25 @@ mod tests {
             name: String::from("proj1"),
             start: 1,
             end: 5,
+            language: None,
+            dir: None,
+            tag: None,
         });
         spans.push(Span {
             name: String::from("proj2"),
             start: 7,
             end: 12,
+            language: None,
+            dir: None,
+            tag: None,
         });
         spans.push(Span {
             name: String::from("proj1"),
             start: 11,
             end: 26,
+            language: None,
+            dir: None,
+            tag: None,
         });
 
         let project_totals = calculate_project_total_time(spans);
@@ -154,4 +241,159 @@ mod tests {
         assert!(project_totals.contains_key(proj_2_name));
         assert_eq!(5u64, *project_totals.get(proj_2_name).unwrap());
     }
+
+    #[test]
+    fn calculate_project_breakdown_time_by_language() {
+        let mut spans = vec![];
+        spans.push(Span {
+            name: String::from("proj1"),
+            start: 1,
+            end: 5,
+            language: Some(String::from("Rust")),
+            dir: None,
+            tag: None,
+        });
+        spans.push(Span {
+            name: String::from("proj1"),
+            start: 5,
+            end: 12,
+            language: Some(String::from("Docs")),
+            dir: None,
+            tag: None,
+        });
+        spans.push(Span {
+            name: String::from("proj1"),
+            start: 20,
+            end: 26,
+            language: Some(String::from("Rust")),
+            dir: None,
+            tag: None,
+        });
+        spans.push(Span {
+            name: String::from("proj2"),
+            start: 30,
+            end: 40,
+            language: None,
+            dir: None,
+            tag: None,
+        });
+
+        let breakdown_totals = calculate_project_breakdown_time(spans, &Breakdown::Language, None);
+
+        assert_eq!(3, breakdown_totals.len());
+        assert_eq!(
+            10u64,
+            breakdown_totals[&(String::from("proj1"), String::from("Rust"))]
+        );
+        assert_eq!(
+            7u64,
+            breakdown_totals[&(String::from("proj1"), String::from("Docs"))]
+        );
+        assert_eq!(
+            10u64,
+            breakdown_totals[&(String::from("proj2"), String::from("Unknown"))]
+        );
+    }
+
+    #[test]
+    fn calculate_project_breakdown_time_single_project() {
+        let mut spans = vec![];
+        spans.push(Span {
+            name: String::from("proj1"),
+            start: 1,
+            end: 5,
+            language: Some(String::from("Rust")),
+            dir: None,
+            tag: None,
+        });
+        spans.push(Span {
+            name: String::from("proj2"),
+            start: 30,
+            end: 40,
+            language: Some(String::from("Rust")),
+            dir: None,
+            tag: None,
+        });
+
+        let breakdown_totals =
+            calculate_project_breakdown_time(spans, &Breakdown::Language, Some("proj2"));
+
+        assert_eq!(1, breakdown_totals.len());
+        assert!(breakdown_totals.contains_key(&(String::from("proj2"), String::from("Rust"))));
+    }
+
+    #[test]
+    fn calculate_project_breakdown_time_by_dir() {
+        let mut spans = vec![];
+        spans.push(Span {
+            name: String::from("proj1"),
+            start: 1,
+            end: 5,
+            language: Some(String::from("Rust")),
+            dir: Some(String::from("crates/a")),
+            tag: None,
+        });
+        spans.push(Span {
+            name: String::from("proj1"),
+            start: 5,
+            end: 12,
+            language: Some(String::from("Docs")),
+            dir: Some(String::from("crates/a")),
+            tag: None,
+        });
+        spans.push(Span {
+            name: String::from("proj1"),
+            start: 20,
+            end: 26,
+            language: Some(String::from("Rust")),
+            dir: Some(String::from(".")),
+            tag: None,
+        });
+
+        let breakdown_totals =
+            calculate_project_breakdown_time(spans, &Breakdown::Dir, Some("proj1"));
+
+        assert_eq!(2, breakdown_totals.len());
+        assert_eq!(
+            11u64,
+            breakdown_totals[&(String::from("proj1"), String::from("crates/a"))]
+        );
+        assert_eq!(
+            6u64,
+            breakdown_totals[&(String::from("proj1"), String::from("."))]
+        );
+    }
+
+    #[test]
+    fn calculate_project_breakdown_time_by_tag() {
+        let mut spans = vec![];
+        spans.push(Span {
+            name: String::from("proj1"),
+            start: 1,
+            end: 5,
+            language: None,
+            dir: None,
+            tag: None,
+        });
+        spans.push(Span {
+            name: String::from("proj1"),
+            start: 20,
+            end: 26,
+            language: None,
+            dir: None,
+            tag: Some(String::from("off-hours")),
+        });
+
+        let breakdown_totals = calculate_project_breakdown_time(spans, &Breakdown::Tag, None);
+
+        assert_eq!(2, breakdown_totals.len());
+        assert_eq!(
+            4u64,
+            breakdown_totals[&(String::from("proj1"), String::from("Untagged"))]
+        );
+        assert_eq!(
+            6u64,
+            breakdown_totals[&(String::from("proj1"), String::from("off-hours"))]
+        );
+    }
 }
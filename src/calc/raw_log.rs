@@ -8,6 +8,10 @@ use std::fmt::Formatter;
 pub struct RawLog {
     pub name: String,
     pub timestamp: u64,
+    pub language: Option<String>,
+    pub dir: Option<String>,
+    /// Marks activity which should be told apart in reports, like `off-hours` activity
+    pub tag: Option<String>,
 }
 
 pub fn raw_logs_from(raw_data: &str) -> Result<Vec<RawLog>, TimeTrackerError> {
@@ -24,25 +28,111 @@ pub fn raw_logs_from(raw_data: &str) -> Result<Vec<RawLog>, TimeTrackerError> {
 impl<'a> TryFrom<&'a str> for RawLog {
     type Error = TimeTrackerError;
     fn try_from(raw_data: &'a str) -> Result<Self, Self::Error> {
-        let mut parts = raw_data.split('/');
+        // the directory is the last part, and may itself contain separators
+        let mut parts = raw_data.splitn(4, '/');
         let name = match parts.next() {
             Some(v) => v.to_string(),
             None => return Err(TimeTrackerError::InvalidLineError(raw_data.to_string())),
         };
-        let timestamp = match parts.next() {
-            Some(v) => match v.parse::<u64>() {
-                Ok(parsed) => parsed,
-                Err(_) => return Err(TimeTrackerError::InvalidTimestampError(v.to_string())),
-            },
+        let (timestamp, tag) = match parts.next() {
+            Some(v) => {
+                let (timestamp, tag) = split_tag(v);
+                match timestamp.parse::<u64>() {
+                    Ok(parsed) => (parsed, tag),
+                    Err(_) => {
+                        return Err(TimeTrackerError::InvalidTimestampError(
+                            timestamp.to_string(),
+                        ))
+                    }
+                }
+            }
             None => return Err(TimeTrackerError::InvalidLineError(raw_data.to_string())),
         };
-        Ok(RawLog { name, timestamp })
+        // logs written before languages were tracked only contain the name and timestamp
+        let language = parts
+            .next()
+            .filter(|v| !v.is_empty())
+            .map(|v| v.to_string());
+        let dir = parts
+            .next()
+            .filter(|v| !v.is_empty())
+            .map(|v| v.to_string());
+        Ok(RawLog {
+            name,
+            timestamp,
+            language,
+            dir,
+            tag,
+        })
+    }
+}
+
+/// Splits the tag from the timestamp it follows, like `100#off-hours`
+///
+/// The directory is the last part of a line and may contain slashes, so the tag can't be added
+/// after it, while a timestamp never contains a `#`.
+pub(crate) fn split_tag(part: &str) -> (&str, Option<String>) {
+    match part.find('#') {
+        Some(index) => (
+            &part[..index],
+            Some(part[index + 1..].to_string()).filter(|tag| !tag.is_empty()),
+        ),
+        None => (part, None),
+    }
+}
+
+impl RawLog {
+    /// Checks the activity can be stored as a single line of raw data
+    ///
+    /// The name and language are separated by slashes and the tag follows the timestamp after a
+    /// `#`, while the directory is the last part and may contain slashes, but none of them can
+    /// contain a newline.
+    pub fn validate(&self) -> Result<(), TimeTrackerError> {
+        if self.name.is_empty() || self.name.contains(&['/', '\n'][..]) {
+            return Err(TimeTrackerError::InvalidProjectError(self.name.clone()));
+        }
+        if let Some(language) = &self.language {
+            if language.contains(&['/', '\n'][..]) {
+                return Err(TimeTrackerError::InvalidActivityError(format!(
+                    "languages can't contain slashes or newlines: {:?}",
+                    language
+                )));
+            }
+        }
+        if let Some(tag) = &self.tag {
+            if tag.contains(&['/', '#', '\n'][..]) {
+                return Err(TimeTrackerError::InvalidActivityError(format!(
+                    "tags can't contain slashes, hashes or newlines: {:?}",
+                    tag
+                )));
+            }
+        }
+        if let Some(dir) = &self.dir {
+            if dir.contains('\n') {
+                return Err(TimeTrackerError::InvalidActivityError(format!(
+                    "directories can't contain newlines: {:?}",
+                    dir
+                )));
+            }
+        }
+
+        Ok(())
     }
 }
 
 impl Display for RawLog {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "{}/{}", self.name, self.timestamp,)
+        write!(f, "{}/{}", self.name, self.timestamp,)?;
+        if let Some(tag) = &self.tag {
+            write!(f, "#{}", tag)?;
+        }
+        if self.language.is_some() || self.dir.is_some() {
+            write!(f, "/{}", self.language.as_deref().unwrap_or(""))?;
+        }
+        if let Some(dir) = &self.dir {
+            write!(f, "/{}", dir)?;
+        }
+        Ok(())
     }
 }
 
@@ -50,6 +140,38 @@ impl Display for RawLog {
 mod tests {
     use super::*;
 
+    #[test]
+    fn validate() {
+        let log = |name: &str, language: Option<&str>, dir: Option<&str>| RawLog {
+            name: name.to_string(),
+            timestamp: 100,
+            language: language.map(String::from),
+            dir: dir.map(String::from),
+            tag: None,
+        };
+
+        assert!(log("testProj", Some("Rust"), Some("src/bin"))
+            .validate()
+            .is_ok());
+        assert!(log("", None, None).validate().is_err());
+        assert!(log("test\nProj", None, None).validate().is_err());
+        assert!(log("testProj", Some("Rust/x"), None).validate().is_err());
+        assert!(log("testProj", Some("Rust\nother/1"), None)
+            .validate()
+            .is_err());
+        assert!(log("testProj", None, Some("src\nother/1"))
+            .validate()
+            .is_err());
+
+        let tagged = |tag: &str| RawLog {
+            tag: Some(tag.to_string()),
+            ..log("testProj", None, None)
+        };
+        assert!(tagged("off-hours").validate().is_ok());
+        assert!(tagged("off/hours").validate().is_err());
+        assert!(tagged("off#hours").validate().is_err());
+    }
+
     #[test]
     fn raw_logs_from_string() {
         let raw_data = "testproj1/123\ntestproj2/456\n";
@@ -65,7 +187,25 @@ mod tests {
         assert_eq!(
             RawLog {
                 name: String::from("josh"),
-                timestamp: 123u64
+                timestamp: 123u64,
+                language: None,
+                dir: None,
+                tag: None,
+            },
+            RawLog::try_from(raw_data).unwrap()
+        );
+    }
+
+    #[test]
+    fn raw_log_from_str_with_language() {
+        let raw_data = "josh/123/Rust";
+        assert_eq!(
+            RawLog {
+                name: String::from("josh"),
+                timestamp: 123u64,
+                language: Some(String::from("Rust")),
+                dir: None,
+                tag: None,
             },
             RawLog::try_from(raw_data).unwrap()
         );
@@ -76,8 +216,67 @@ mod tests {
         let raw_log = RawLog {
             name: String::from("testproj1"),
             timestamp: 123,
+            language: None,
+            dir: None,
+            tag: None,
         };
 
         assert_eq!("testproj1/123", format!("{}", raw_log));
     }
+
+    #[test]
+    fn raw_log_display_with_language() {
+        let raw_log = RawLog {
+            name: String::from("testproj1"),
+            timestamp: 123,
+            language: Some(String::from("Docs")),
+            dir: None,
+            tag: None,
+        };
+
+        assert_eq!("testproj1/123/Docs", format!("{}", raw_log));
+    }
+
+    #[test]
+    fn raw_log_from_str_with_dir() {
+        let raw_data = "josh/123//crates/core";
+        assert_eq!(
+            RawLog {
+                name: String::from("josh"),
+                timestamp: 123u64,
+                language: None,
+                dir: Some(String::from("crates/core")),
+                tag: None,
+            },
+            RawLog::try_from(raw_data).unwrap()
+        );
+    }
+
+    #[test]
+    fn raw_log_display_with_dir() {
+        let raw_log = RawLog {
+            name: String::from("testproj1"),
+            timestamp: 123,
+            language: Some(String::from("Rust")),
+            dir: Some(String::from("crates/core")),
+            tag: None,
+        };
+
+        assert_eq!("testproj1/123/Rust/crates/core", format!("{}", raw_log));
+    }
+
+    #[test]
+    fn raw_log_with_tag() {
+        let raw_log = RawLog {
+            name: String::from("testproj1"),
+            timestamp: 123,
+            language: Some(String::from("Rust")),
+            dir: Some(String::from("crates/core")),
+            tag: Some(String::from("off-hours")),
+        };
+
+        let line = format!("{}", raw_log);
+        assert_eq!("testproj1/123#off-hours/Rust/crates/core", line);
+        assert_eq!(raw_log, RawLog::try_from(line.as_str()).unwrap());
+    }
 }
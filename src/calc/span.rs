@@ -1,4 +1,4 @@
-use crate::calc::raw_log::RawLog;
+use crate::calc::raw_log::{split_tag, RawLog};
 use crate::TimeTrackerError;
 use std::cmp::max;
 use std::cmp::min;
@@ -14,6 +14,9 @@ pub struct Span {
     pub name: String,
     pub start: u64,
     pub end: u64,
+    pub language: Option<String>,
+    pub dir: Option<String>,
+    pub tag: Option<String>,
 }
 
 impl Span {
@@ -24,7 +27,17 @@ impl Span {
 
 impl Display for Span {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "{}/{}/{}", self.name, self.start, self.end,)
+        write!(f, "{}/{}/{}", self.name, self.start, self.end,)?;
+        if let Some(tag) = &self.tag {
+            write!(f, "#{}", tag)?;
+        }
+        if self.language.is_some() || self.dir.is_some() {
+            write!(f, "/{}", self.language.as_deref().unwrap_or(""))?;
+        }
+        if let Some(dir) = &self.dir {
+            write!(f, "/{}", dir)?;
+        }
+        Ok(())
     }
 }
 
@@ -41,7 +54,8 @@ pub fn spans_from(processed_data: &str) -> Result<Vec<Span>, TimeTrackerError> {
 impl<'a> TryFrom<&'a str> for Span {
     type Error = TimeTrackerError;
     fn try_from(raw_data: &'a str) -> Result<Self, Self::Error> {
-        let mut parts = raw_data.split('/');
+        // the directory is the last part, and may itself contain separators
+        let mut parts = raw_data.splitn(5, '/');
         let name = match parts.next() {
             Some(v) => v.to_string(),
             None => return Err(TimeTrackerError::InvalidLineError(raw_data.to_string())),
@@ -53,14 +67,33 @@ impl<'a> TryFrom<&'a str> for Span {
             },
             None => return Err(TimeTrackerError::InvalidLineError(raw_data.to_string())),
         };
-        let end = match parts.next() {
-            Some(v) => match v.parse::<u64>() {
-                Ok(parsed) => parsed,
-                Err(_) => return Err(TimeTrackerError::InvalidTimestampError(v.to_string())),
-            },
+        // like raw data, the tag follows the last timestamp
+        let (end, tag) = match parts.next() {
+            Some(v) => {
+                let (end, tag) = split_tag(v);
+                match end.parse::<u64>() {
+                    Ok(parsed) => (parsed, tag),
+                    Err(_) => return Err(TimeTrackerError::InvalidTimestampError(end.to_string())),
+                }
+            }
             None => return Err(TimeTrackerError::InvalidLineError(raw_data.to_string())),
         };
-        Ok(Span { name, start, end })
+        let language = parts
+            .next()
+            .filter(|v| !v.is_empty())
+            .map(|v| v.to_string());
+        let dir = parts
+            .next()
+            .filter(|v| !v.is_empty())
+            .map(|v| v.to_string());
+        Ok(Span {
+            name,
+            start,
+            end,
+            language,
+            dir,
+            tag,
+        })
     }
 }
 
@@ -77,9 +110,16 @@ pub fn get_spans_from(mut raw_logs: Vec<RawLog>) -> Vec<Span> {
         name: first_log.name,
         start: first_log.timestamp,
         end: first_log.timestamp,
+        language: first_log.language,
+        dir: first_log.dir,
+        tag: first_log.tag,
     };
     for log in raw_logs {
-        let same_name = log.name == span.name;
+        // a change of language, directory or tag within a project starts a new span so time can be broken down by them
+        let same_name = log.name == span.name
+            && log.language == span.language
+            && log.dir == span.dir
+            && log.tag == span.tag;
         let small_time_gap =
             log.timestamp.saturating_sub(span.end) < MAX_SECONDS_BETWEEN_RECORDS_IN_SPAN;
 
@@ -95,6 +135,9 @@ pub fn get_spans_from(mut raw_logs: Vec<RawLog>) -> Vec<Span> {
                     name: log.name,
                     start: mid_point_time,
                     end: log.timestamp,
+                    language: log.language,
+                    dir: log.dir,
+                    tag: log.tag,
                 };
             }
             (_, false) => {
@@ -103,6 +146,9 @@ pub fn get_spans_from(mut raw_logs: Vec<RawLog>) -> Vec<Span> {
                     name: log.name,
                     start: log.timestamp,
                     end: log.timestamp,
+                    language: log.language,
+                    dir: log.dir,
+                    tag: log.tag,
                 };
             }
         };
@@ -111,22 +157,25 @@ pub fn get_spans_from(mut raw_logs: Vec<RawLog>) -> Vec<Span> {
     spans
 }
 
-pub fn get_last_timestamp_per_project(spans: &[Span]) -> HashMap<String, u64> {
-    let mut map = HashMap::new();
+pub fn get_last_timestamp_per_project(spans: &[Span]) -> HashMap<String, RawLog> {
+    let mut map: HashMap<String, RawLog> = HashMap::new();
 
     for span in spans {
-        // TODO is there a more efficient way to do this?
-        match map.remove(&span.name) {
-            None => {
-                map.insert(span.name.clone(), span.end);
-            }
-            Some(map_time) => {
-                if map_time < span.end {
-                    map.insert(span.name.clone(), span.end);
-                } else {
-                    map.insert(span.name.clone(), map_time);
-                }
-            }
+        let is_latest = match map.get(&span.name) {
+            None => true,
+            Some(raw_log) => raw_log.timestamp < span.end,
+        };
+        if is_latest {
+            map.insert(
+                span.name.clone(),
+                RawLog {
+                    name: span.name.clone(),
+                    timestamp: span.end,
+                    language: span.language.clone(),
+                    dir: span.dir.clone(),
+                    tag: span.tag.clone(),
+                },
+            );
         }
     }
 
@@ -134,15 +183,9 @@ pub fn get_last_timestamp_per_project(spans: &[Span]) -> HashMap<String, u64> {
 }
 
 pub fn get_vec_raw_logs_from_map_last_timestamp_per_project(
-    map: HashMap<String, u64>,
+    map: HashMap<String, RawLog>,
 ) -> Vec<RawLog> {
-    let mut raw_logs: Vec<RawLog> = map
-        .into_iter()
-        .map(|(project_name, timestamp)| RawLog {
-            name: project_name,
-            timestamp,
-        })
-        .collect();
+    let mut raw_logs: Vec<RawLog> = map.into_values().collect();
 
     raw_logs.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
 
@@ -165,14 +208,23 @@ mod tests {
         let raw_log_1 = RawLog {
             name: String::from(project_name),
             timestamp: 0,
+            language: None,
+            dir: None,
+            tag: None,
         };
         let raw_log_2 = RawLog {
             name: String::from(project_name),
             timestamp: 5,
+            language: None,
+            dir: None,
+            tag: None,
         };
         let raw_log_3 = RawLog {
             name: String::from(project_name),
             timestamp: 20,
+            language: None,
+            dir: None,
+            tag: None,
         };
         let raw_logs = vec![raw_log_1, raw_log_2, raw_log_3];
 
@@ -193,18 +245,30 @@ mod tests {
         let raw_log_1 = RawLog {
             name: String::from(project_name),
             timestamp: 0,
+            language: None,
+            dir: None,
+            tag: None,
         };
         let raw_log_2 = RawLog {
             name: String::from(project_name),
             timestamp: 6,
+            language: None,
+            dir: None,
+            tag: None,
         };
         let raw_log_3 = RawLog {
             name: String::from(project_2_name),
             timestamp: 18,
+            language: None,
+            dir: None,
+            tag: None,
         };
         let raw_log_4 = RawLog {
             name: String::from(project_2_name),
             timestamp: 26,
+            language: None,
+            dir: None,
+            tag: None,
         };
         let raw_logs = vec![raw_log_1, raw_log_2, raw_log_3, raw_log_4];
 
@@ -228,26 +292,44 @@ mod tests {
         let raw_log_1 = RawLog {
             name: String::from(project_1_name),
             timestamp: 0,
+            language: None,
+            dir: None,
+            tag: None,
         };
         let raw_log_2 = RawLog {
             name: String::from(project_1_name),
             timestamp: 5,
+            language: None,
+            dir: None,
+            tag: None,
         };
         let raw_log_3 = RawLog {
             name: String::from(project_2_name),
             timestamp: 20,
+            language: None,
+            dir: None,
+            tag: None,
         };
         let raw_log_4 = RawLog {
             name: String::from(project_2_name),
             timestamp: 24,
+            language: None,
+            dir: None,
+            tag: None,
         };
         let raw_log_5 = RawLog {
             name: String::from(project_1_name),
             timestamp: 30,
+            language: None,
+            dir: None,
+            tag: None,
         };
         let raw_log_6 = RawLog {
             name: String::from(project_1_name),
             timestamp: 36,
+            language: None,
+            dir: None,
+            tag: None,
         };
         let raw_logs = vec![
             raw_log_1, raw_log_2, raw_log_3, raw_log_4, raw_log_5, raw_log_6,
@@ -281,18 +363,30 @@ mod tests {
         let raw_log_1 = RawLog {
             name: String::from(project_name),
             timestamp: 0,
+            language: None,
+            dir: None,
+            tag: None,
         };
         let raw_log_2 = RawLog {
             name: String::from(project_name),
             timestamp: 5,
+            language: None,
+            dir: None,
+            tag: None,
         };
         let raw_log_3 = RawLog {
             name: String::from(project_name),
             timestamp: 555520,
+            language: None,
+            dir: None,
+            tag: None,
         };
         let raw_log_4 = RawLog {
             name: String::from(project_name),
             timestamp: 555526,
+            language: None,
+            dir: None,
+            tag: None,
         };
         let raw_logs = vec![raw_log_1, raw_log_2, raw_log_3, raw_log_4];
 
@@ -324,16 +418,25 @@ mod tests {
             name: String::from("testproj1"),
             start: 0,
             end: 30,
+            language: None,
+            dir: None,
+            tag: None,
         };
         let span1b = Span {
             name: String::from("testproj1"),
             start: 10030,
             end: 10060,
+            language: None,
+            dir: None,
+            tag: None,
         };
         let span2a = Span {
             name: String::from("testproj2"),
             start: 530,
             end: 560,
+            language: None,
+            dir: None,
+            tag: None,
         };
 
         spans.push(span1a);
@@ -343,10 +446,11 @@ mod tests {
         let last_timestamp_per_project = get_last_timestamp_per_project(&spans);
 
         assert_eq!(
-            &10060u64,
+            10060u64,
             last_timestamp_per_project
                 .get("testproj1")
                 .expect("testproj1 not found")
+                .timestamp
         );
     }
 
@@ -363,9 +467,36 @@ mod tests {
     #[test]
     fn get_vec_raw_logs_from_map_last_timestamp_per_project_several_projects() {
         let mut last_timestamp_per_project = HashMap::new();
-        last_timestamp_per_project.insert(String::from("proj1"), 1);
-        last_timestamp_per_project.insert(String::from("proj2"), 2);
-        last_timestamp_per_project.insert(String::from("proj3"), 3);
+        last_timestamp_per_project.insert(
+            String::from("proj1"),
+            RawLog {
+                name: String::from("proj1"),
+                timestamp: 1,
+                language: None,
+                dir: None,
+                tag: None,
+            },
+        );
+        last_timestamp_per_project.insert(
+            String::from("proj2"),
+            RawLog {
+                name: String::from("proj2"),
+                timestamp: 2,
+                language: None,
+                dir: None,
+                tag: None,
+            },
+        );
+        last_timestamp_per_project.insert(
+            String::from("proj3"),
+            RawLog {
+                name: String::from("proj3"),
+                timestamp: 3,
+                language: None,
+                dir: None,
+                tag: None,
+            },
+        );
 
         let last_timestamp_as_vec =
             get_vec_raw_logs_from_map_last_timestamp_per_project(last_timestamp_per_project);
@@ -373,4 +504,115 @@ mod tests {
         assert_eq!(3, last_timestamp_as_vec.len());
         assert_eq!(1, last_timestamp_as_vec.get(0).unwrap().timestamp);
     }
+
+    #[test]
+    fn raw_log_to_span_language_change() {
+        let project_name = "test_proj";
+        let raw_log_1 = RawLog {
+            name: String::from(project_name),
+            timestamp: 0,
+            language: Some(String::from("Rust")),
+            dir: None,
+            tag: None,
+        };
+        let raw_log_2 = RawLog {
+            name: String::from(project_name),
+            timestamp: 10,
+            language: Some(String::from("Docs")),
+            dir: None,
+            tag: None,
+        };
+        let raw_log_3 = RawLog {
+            name: String::from(project_name),
+            timestamp: 14,
+            language: Some(String::from("Docs")),
+            dir: None,
+            tag: None,
+        };
+        let raw_logs = vec![raw_log_1, raw_log_2, raw_log_3];
+
+        let mut spans = get_spans_from(raw_logs);
+
+        assert_eq!(2, spans.len());
+
+        let span_1 = spans.remove(0);
+        assert_eq!(Some(String::from("Rust")), span_1.language);
+        assert_eq!(5, span_1.duration());
+
+        let span_2 = spans.remove(0);
+        assert_eq!(Some(String::from("Docs")), span_2.language);
+        assert_eq!(9, span_2.duration());
+    }
+
+    #[test]
+    fn span_from_str_with_language() {
+        let span = Span::try_from("testproj1/10/20/Rust").unwrap();
+
+        assert_eq!("testproj1", span.name);
+        assert_eq!(10, span.duration());
+        assert_eq!(Some(String::from("Rust")), span.language);
+    }
+
+    #[test]
+    fn span_display_without_language() {
+        let span = Span {
+            name: String::from("testproj1"),
+            start: 10,
+            end: 20,
+            language: None,
+            dir: None,
+            tag: None,
+        };
+
+        assert_eq!("testproj1/10/20", format!("{}", span));
+    }
+
+    #[test]
+    fn raw_log_to_span_dir_change() {
+        let project_name = "test_proj";
+        let raw_log_1 = RawLog {
+            name: String::from(project_name),
+            timestamp: 0,
+            language: None,
+            dir: Some(String::from("crates/a")),
+            tag: None,
+        };
+        let raw_log_2 = RawLog {
+            name: String::from(project_name),
+            timestamp: 10,
+            language: None,
+            dir: Some(String::from("crates/b")),
+            tag: None,
+        };
+        let raw_logs = vec![raw_log_1, raw_log_2];
+
+        let mut spans = get_spans_from(raw_logs);
+
+        assert_eq!(2, spans.len());
+
+        let span_1 = spans.remove(0);
+        assert_eq!(Some(String::from("crates/a")), span_1.dir);
+        assert_eq!(5, span_1.duration());
+
+        let span_2 = spans.remove(0);
+        assert_eq!(Some(String::from("crates/b")), span_2.dir);
+        assert_eq!(5, span_2.duration());
+    }
+
+    #[test]
+    fn span_from_str_with_dir() {
+        let span = Span::try_from("testproj1/10/20/Rust/crates/core").unwrap();
+
+        assert_eq!(Some(String::from("Rust")), span.language);
+        assert_eq!(Some(String::from("crates/core")), span.dir);
+    }
+
+    #[test]
+    fn span_with_tag() {
+        let span = Span::try_from("testproj1/10/20#off-hours/Rust").unwrap();
+
+        assert_eq!(20, span.end);
+        assert_eq!(Some(String::from("off-hours")), span.tag);
+        assert_eq!("testproj1/10/20#off-hours/Rust", format!("{}", span));
+    }
 }
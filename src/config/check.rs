@@ -0,0 +1,400 @@
+use super::hours::WorkingHours;
+use super::path::{normalize_path, strip_path_prefix};
+use super::{is_valid_profile_name, UserConfig};
+use crate::TimeTrackerError;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use toml::Value;
+
+/// The keys `UserConfig` reads, anything else is probably a typo
+const USER_CONFIG_KEYS: &[&str] = &[
+    "track_paths",
+    "dir_depth",
+    "watcher",
+    "poll_interval_secs",
+    "ignore",
+    "batch_window_ms",
+    "heartbeat_port",
+    "working_hours",
+    "profiles",
+];
+
+/// The keys `ProfileConfig` reads
+const PROFILE_CONFIG_KEYS: &[&str] = &[
+    "track_paths",
+    "dir_depth",
+    "watcher",
+    "poll_interval_secs",
+    "ignore",
+    "batch_window_ms",
+    "heartbeat_port",
+    "working_hours",
+];
+
+/// A problem found in the user configuration file
+#[derive(Debug, PartialEq)]
+pub enum Problem {
+    /// The configuration can't be used, or would record activity twice
+    Error(String),
+    /// The configuration works, but probably not as intended
+    Warning(String),
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::Error(message) => write!(f, "error: {}", message),
+            Problem::Warning(message) => write!(f, "warning: {}", message),
+        }
+    }
+}
+
+/// Checks the user configuration file, printing every problem found
+///
+/// Fails if there are any errors, or with `strict` if there are any warnings, so it can be used to
+/// check a configuration file in CI.
+pub fn check_config(user_config_path: &Path, strict: bool) -> Result<(), TimeTrackerError> {
+    let problems = match fs::read_to_string(user_config_path) {
+        Ok(contents) => check_user_config(&contents),
+        Err(err) => vec![Problem::Error(format!("could not read the file ({})", err))],
+    };
+
+    for problem in &problems {
+        println!("{}: {}", user_config_path.display(), problem);
+    }
+
+    let failures = problems
+        .iter()
+        .filter(|problem| strict || matches!(problem, Problem::Error(_)))
+        .count();
+    if failures > 0 {
+        return Err(TimeTrackerError::InvalidConfigError(format!(
+            "{} {} found in {}",
+            failures,
+            if failures == 1 { "problem" } else { "problems" },
+            user_config_path.display()
+        )));
+    }
+
+    println!("{}: ok", user_config_path.display());
+    Ok(())
+}
+
+pub fn check_user_config(contents: &str) -> Vec<Problem> {
+    let document = match contents.parse::<Value>() {
+        Ok(document) => document,
+        Err(err) => return vec![Problem::Error(describe_toml_error(&err, contents))],
+    };
+    let mut problems = vec![];
+
+    match toml::from_str::<UserConfig>(contents) {
+        Ok(user_config) => {
+            let working_hours = user_config.working_hours.iter().chain(
+                user_config
+                    .profiles
+                    .values()
+                    .filter_map(|profile| profile.working_hours.as_ref()),
+            );
+            for working_hours in working_hours {
+                match WorkingHours::new(Some(working_hours)) {
+                    Ok(_) => {}
+                    Err(TimeTrackerError::InvalidConfigError(message)) => {
+                        problems.push(Problem::Error(message))
+                    }
+                    Err(err) => problems.push(Problem::Error(err.to_string())),
+                }
+            }
+        }
+        Err(err) => problems.push(Problem::Error(describe_toml_error(&err, contents))),
+    }
+
+    problems.extend(unknown_keys(contents, &document, USER_CONFIG_KEYS, ""));
+    let mut profile_track_paths = vec![(String::from("default"), track_paths(&document))];
+    if let Some(profiles) = document.get("profiles").and_then(Value::as_table) {
+        for (name, profile) in profiles {
+            if !is_valid_profile_name(name) {
+                problems.push(Problem::Error(format!(
+                    "profile names can only contain letters, numbers, - and _: {:?}",
+                    name
+                )));
+            }
+            let prefix = format!("profiles.{}.", name);
+            problems.extend(unknown_keys(
+                contents,
+                profile,
+                PROFILE_CONFIG_KEYS,
+                &prefix,
+            ));
+            profile_track_paths.push((format!("{} profile", name), track_paths(profile)));
+        }
+    }
+
+    problems.extend(check_track_paths(&profile_track_paths));
+    problems
+}
+
+fn unknown_keys(contents: &str, table: &Value, known_keys: &[&str], prefix: &str) -> Vec<Problem> {
+    let table = match table.as_table() {
+        Some(table) => table,
+        None => return vec![],
+    };
+
+    table
+        .keys()
+        .filter(|key| !known_keys.contains(&key.as_str()))
+        .map(|key| {
+            let location = find_key(contents, &format!("{}{}", prefix, key))
+                .map(|(line, _)| format!("line {}: ", line))
+                .unwrap_or_default();
+            Problem::Warning(format!("{}unknown key `{}{}`", location, prefix, key))
+        })
+        .collect()
+}
+
+/// Describes a TOML error as `line L, column C: message`, with a 1-based location
+///
+/// Errors in values only name their key, like ``for key `profiles.work.watcher` ``, so the
+/// location is the value assigned to the key.
+pub fn describe_toml_error(err: &toml::de::Error, contents: &str) -> String {
+    let message = err.to_string();
+    if let Some((line, col)) = err.line_col() {
+        let suffix = format!(" at line {}", line + 1);
+        let message = message.trim_end_matches(suffix.as_str());
+        return format!("line {}, column {}: {}", line + 1, col + 1, message);
+    }
+
+    let key = message
+        .find("for key `")
+        .map(|start| message[start + "for key `".len()..].trim_end_matches('`'));
+    match key.and_then(|key| find_key(contents, key)) {
+        Some((line, col)) => format!("line {}, column {}: {}", line, col, message),
+        None => message,
+    }
+}
+
+/// Finds the 1-based line and column of the value assigned to a dotted key like
+/// `profiles.work.watcher`, since parsed TOML values don't keep their location
+///
+/// Keys are resolved within the `[table]` headers above them, so a key set at the top level and
+/// in a profile is found in the right place.
+fn find_key(contents: &str, key: &str) -> Option<(usize, usize)> {
+    let key = split_key(key);
+    let mut table = vec![];
+
+    for (index, line) in contents.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with('[') {
+            let header = trimmed.trim_start_matches('[');
+            table = split_key(&header[..header.find(']')?]);
+            continue;
+        }
+
+        let equals = match line.find('=') {
+            Some(equals) if !trimmed.starts_with('#') => equals,
+            _ => continue,
+        };
+        let mut line_key = table.clone();
+        line_key.extend(split_key(&line[..equals]));
+        if line_key == key {
+            let value = &line[equals + 1..];
+            let col = line[..equals + 1].chars().count()
+                + value.chars().take_while(|c| c.is_whitespace()).count();
+            return Some((index + 1, col + 1));
+        }
+    }
+
+    None
+}
+
+/// Splits a dotted key into its parts, without the quotes of quoted parts
+fn split_key(key: &str) -> Vec<String> {
+    key.split('.')
+        .map(|part| part.trim().trim_matches('"').to_string())
+        .collect()
+}
+
+fn track_paths(table: &Value) -> Vec<PathBuf> {
+    table
+        .get("track_paths")
+        .and_then(Value::as_array)
+        .map(|track_paths| {
+            track_paths
+                .iter()
+                .filter_map(Value::as_str)
+                .map(PathBuf::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Missing track paths are only a warning since they may be created later, like a mounted drive,
+/// but nested track paths record the same changes twice
+fn check_track_paths(profile_track_paths: &[(String, Vec<PathBuf>)]) -> Vec<Problem> {
+    let mut problems = vec![];
+    let mut checked: Vec<(&str, PathBuf)> = vec![];
+
+    for (profile, track_paths) in profile_track_paths {
+        for track_path in track_paths {
+            if !track_path.is_dir() {
+                problems.push(Problem::Warning(format!(
+                    "the track path {:?} ({}) is not a directory",
+                    track_path, profile
+                )));
+            }
+
+            let track_path = normalize_path(track_path);
+            for (other_profile, other_path) in &checked {
+                if strip_path_prefix(&track_path, other_path).is_some()
+                    || strip_path_prefix(other_path, &track_path).is_some()
+                {
+                    problems.push(Problem::Error(format!(
+                        "the track paths {:?} ({}) and {:?} ({}) overlap, so changes would be recorded twice",
+                        other_path, other_profile, track_path, profile
+                    )));
+                }
+            }
+            checked.push((profile, track_path));
+        }
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn valid_config() {
+        let dir = tempdir().unwrap();
+        let contents = format!(
+            "track_paths = [{:?}]\ndir_depth = 2\n",
+            normalize_path(dir.path())
+        );
+
+        assert_eq!(Vec::<Problem>::new(), check_user_config(&contents));
+    }
+
+    #[test]
+    fn syntax_error_location() {
+        let problems = check_user_config("track_paths = [\"/Users/josh/Projects\"]\ndir_depth 2\n");
+
+        match problems.as_slice() {
+            [Problem::Error(message)] => assert!(message.starts_with("line 2, column 11: ")),
+            _ => panic!("Expected a single error, found {:?}", problems),
+        }
+    }
+
+    #[test]
+    fn invalid_values() {
+        let dir = tempdir().unwrap();
+        let contents = format!(
+            "track_paths = [{:?}]\nwatcher = \"inotify\"\n",
+            normalize_path(dir.path())
+        );
+
+        match check_user_config(&contents).as_slice() {
+            [Problem::Error(message)] => {
+                assert!(message.starts_with("line 2, column 11: ") && message.contains("inotify"))
+            }
+            problems => panic!("Expected a single error, found {:?}", problems),
+        }
+    }
+
+    #[test]
+    fn invalid_profile_values() {
+        let contents =
+            "track_paths = []\nwatcher = \"poll\"\n\n[profiles.work]\ntrack_paths = []\nwatcher = \"bogus\"\n";
+
+        match check_user_config(contents).as_slice() {
+            [Problem::Error(message)] => {
+                assert!(message.starts_with("line 6, column 11: ") && message.contains("bogus"))
+            }
+            problems => panic!("Expected a single error, found {:?}", problems),
+        }
+    }
+
+    #[test]
+    fn find_keys() {
+        let contents =
+            "dir_depth = 1\n# dir_depth = 2\n\n[profiles.\"client-a\"]\n  dir_depth =  3\n";
+
+        assert_eq!(Some((1, 13)), find_key(contents, "dir_depth"));
+        assert_eq!(
+            Some((5, 16)),
+            find_key(contents, "profiles.client-a.dir_depth")
+        );
+        assert_eq!(None, find_key(contents, "profiles.work.dir_depth"));
+    }
+
+    #[test]
+    fn unknown_keys_and_missing_paths() {
+        let dir = tempdir().unwrap();
+        let contents = format!(
+            "track_paths = [{:?}]\ndir_dept = 2\n\n[profiles.work]\ntrack_paths = [{:?}]\nignroe = []\n",
+            normalize_path(dir.path()).join("Projects"),
+            normalize_path(dir.path()).join("Clients")
+        );
+        fs::create_dir(dir.path().join("Projects")).unwrap();
+
+        let problems = check_user_config(&contents);
+
+        assert_eq!(3, problems.len());
+        assert_eq!(
+            Problem::Warning(String::from("line 2: unknown key `dir_dept`")),
+            problems[0]
+        );
+        assert_eq!(
+            Problem::Warning(String::from("line 6: unknown key `profiles.work.ignroe`")),
+            problems[1]
+        );
+        match &problems[2] {
+            Problem::Warning(message) => assert!(message.contains("Clients")),
+            problem => panic!("Expected a missing path warning, found {:?}", problem),
+        }
+    }
+
+    #[test]
+    fn nested_track_paths() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("Projects")).unwrap();
+        let contents = format!(
+            "track_paths = [{:?}]\n\n[profiles.work]\ntrack_paths = [{:?}]\n",
+            normalize_path(dir.path()),
+            normalize_path(dir.path()).join("Projects")
+        );
+
+        match check_user_config(&contents).as_slice() {
+            [Problem::Error(message)] => assert!(message.contains("overlap")),
+            problems => panic!("Expected a single error, found {:?}", problems),
+        }
+    }
+
+    #[test]
+    fn invalid_working_hours() {
+        let contents = "track_paths = []\n\n[working_hours]\nmonday = [\"17:00-09:00\"]\n";
+
+        match check_user_config(contents).as_slice() {
+            [Problem::Error(message)] => assert!(message.contains("17:00-09:00")),
+            problems => panic!("Expected a single error, found {:?}", problems),
+        }
+    }
+
+    #[test]
+    fn invalid_profile_name() {
+        let dir = tempdir().unwrap();
+        let contents = format!(
+            "track_paths = []\n\n[profiles.\"client/a\"]\ntrack_paths = [{:?}]\n",
+            normalize_path(dir.path())
+        );
+
+        match check_user_config(&contents).as_slice() {
+            [Problem::Error(message)] => assert!(message.contains("client/a")),
+            problems => panic!("Expected a single error, found {:?}", problems),
+        }
+    }
+}
@@ -0,0 +1,446 @@
+use super::check::{check_user_config, Problem};
+use super::path::{normalize_path, strip_path_prefix};
+use crate::TimeTracker;
+use crate::TimeTrackerError;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use toml_edit::{Array, DocumentMut, Item, TableLike, Value};
+
+/// The settings which `config set` can change, the track paths are changed with `config add-path`
+const SETTABLE_KEYS: &[&str] = &[
+    "dir_depth",
+    "watcher",
+    "poll_interval_secs",
+    "ignore",
+    "batch_window_ms",
+    "heartbeat_port",
+    "working_hours",
+];
+
+impl<'a> TimeTracker<'a> {
+    /// Adds an existing directory to the track paths of the selected profile
+    ///
+    /// The directory can't overlap the track paths of any profile, which is checked along with the
+    /// rest of the configuration before it is written.
+    pub fn add_track_path<T>(&self, dir: T) -> Result<(), TimeTrackerError>
+    where
+        T: AsRef<Path>,
+    {
+        let dir = dir.as_ref();
+        if !dir.is_dir() {
+            return Err(invalid(format!("{:?} is not a directory", dir)));
+        }
+        let dir = normalize_path(dir);
+
+        self.edit_settings(|settings| {
+            let track_paths = track_paths(settings)?;
+            for track_path in track_paths.iter().map(normalize_path) {
+                if track_path == dir {
+                    return Err(invalid(format!("{:?} is already tracked", dir)));
+                }
+                // nested track paths would record the same changes twice
+                if strip_path_prefix(&dir, &track_path).is_some() {
+                    return Err(invalid(format!(
+                        "{:?} is inside the track path {:?}",
+                        dir, track_path
+                    )));
+                }
+                if strip_path_prefix(&track_path, &dir).is_some() {
+                    return Err(invalid(format!(
+                        "{:?} contains the track path {:?}",
+                        dir, track_path
+                    )));
+                }
+            }
+
+            track_paths_array(settings).push(dir.to_string_lossy().as_ref());
+            Ok(())
+        })?;
+
+        println!("Added {:?} to the track paths", dir);
+        Ok(())
+    }
+
+    /// Removes a directory from the track paths of the selected profile, even if it no longer exists
+    pub fn remove_track_path<T>(&self, dir: T) -> Result<(), TimeTrackerError>
+    where
+        T: AsRef<Path>,
+    {
+        let dir = normalize_path(dir);
+
+        self.edit_settings(|settings| {
+            let track_paths = track_paths(settings)?;
+            if !track_paths
+                .iter()
+                .any(|track_path| normalize_path(track_path) == dir)
+            {
+                return Err(invalid(format!("{:?} is not a track path", dir)));
+            }
+
+            track_paths_array(settings).retain(|track_path| {
+                track_path
+                    .as_str()
+                    .is_none_or(|track_path| normalize_path(track_path) != dir)
+            });
+            Ok(())
+        })?;
+
+        println!("Removed {:?} from the track paths", dir);
+        Ok(())
+    }
+
+    /// Sets a setting of the selected profile
+    ///
+    /// The value is read as TOML, like `2` or `["node_modules", "target"]`, falling back to a
+    /// string so `poll` doesn't need quotes.
+    pub fn set_config(&self, key: &str, value: &str) -> Result<(), TimeTrackerError> {
+        if !SETTABLE_KEYS.contains(&key) {
+            return Err(invalid(format!(
+                "{:?} can't be set, the settings are {}",
+                key,
+                SETTABLE_KEYS.join(", ")
+            )));
+        }
+        let value = parse_value(value);
+
+        self.edit_settings(|settings| {
+            let mut value = value.clone();
+            // a comment after the old value is kept after the new one
+            if let Some(old_value) = settings.get(key).and_then(Item::as_value) {
+                *value.decor_mut() = old_value.decor().clone();
+            }
+            settings.insert(key, Item::Value(value));
+            Ok(())
+        })?;
+
+        println!("Set {} = {}", key, value);
+        Ok(())
+    }
+
+    /// Opens the user configuration file in `$VISUAL` or `$EDITOR`, and checks it afterwards
+    pub fn edit_config(&self) -> Result<(), TimeTrackerError> {
+        let editor = env::var("VISUAL")
+            .or_else(|_| env::var("EDITOR"))
+            .unwrap_or_else(|_| String::from("vi"));
+        // editors are often configured with arguments, like `code --wait`
+        let mut editor_args = editor.split_whitespace();
+        let program = editor_args
+            .next()
+            .ok_or_else(|| invalid(String::from("the editor is empty")))?;
+
+        let status = Command::new(program)
+            .args(editor_args)
+            .arg(&self.config.user_config_path)
+            .status()
+            .map_err(|err| invalid(format!("failed to run {:?} ({})", editor, err)))?;
+        if !status.success() {
+            return Err(invalid(format!("{:?} exited with {}", editor, status)));
+        }
+
+        self.config.reload()?;
+        println!("Configuration saved");
+        Ok(())
+    }
+
+    /// Changes the settings of the selected profile, and writes the file if `config check` would
+    /// find no errors in the result
+    ///
+    /// The file is edited as TOML rather than `UserConfig`, so keys this version doesn't know
+    /// about are kept, along with comments and the formatting of everything which isn't changed.
+    fn edit_settings<F>(&self, edit: F) -> Result<(), TimeTrackerError>
+    where
+        F: FnOnce(&mut dyn TableLike) -> Result<(), TimeTrackerError>,
+    {
+        let path = &self.config.user_config_path;
+        let mut document = read_document(path)?;
+        edit(settings_table(
+            &mut document,
+            self.config.profile.as_deref(),
+        )?)?;
+
+        let contents = document.to_string();
+        let errors: Vec<String> = check_user_config(&contents)
+            .into_iter()
+            .filter_map(|problem| match problem {
+                Problem::Error(message) => Some(message),
+                Problem::Warning(_) => None,
+            })
+            .collect();
+        if !errors.is_empty() {
+            return Err(invalid(errors.join("; ")));
+        }
+
+        fs::write(path, contents).map_err(|err| invalid(err.to_string()))
+    }
+}
+
+fn invalid(message: String) -> TimeTrackerError {
+    TimeTrackerError::InvalidConfigError(message)
+}
+
+fn read_document(path: &Path) -> Result<DocumentMut, TimeTrackerError> {
+    fs::read_to_string(path)
+        .map_err(|err| invalid(err.to_string()))?
+        .parse::<DocumentMut>()
+        .map_err(|err| invalid(err.to_string()))
+}
+
+/// The top level table for the default profile, or the table of the named profile
+fn settings_table<'d>(
+    document: &'d mut DocumentMut,
+    profile: Option<&str>,
+) -> Result<&'d mut dyn TableLike, TimeTrackerError> {
+    let table = match profile {
+        Some(profile) => document
+            .get_mut("profiles")
+            .and_then(|profiles| profiles.get_mut(profile))
+            .ok_or_else(|| TimeTrackerError::UnknownProfileError(profile.to_string()))?,
+        None => document.as_item_mut(),
+    };
+
+    table
+        .as_table_like_mut()
+        .ok_or_else(|| invalid(String::from("expected a table of settings")))
+}
+
+fn track_paths(settings: &dyn TableLike) -> Result<Vec<PathBuf>, TimeTrackerError> {
+    match settings.get("track_paths") {
+        None => Ok(vec![]),
+        Some(item) => match item.as_array() {
+            Some(track_paths) => track_paths
+                .iter()
+                .map(|track_path| {
+                    track_path
+                        .as_str()
+                        .map(PathBuf::from)
+                        .ok_or_else(|| invalid(String::from("track paths must be strings")))
+                })
+                .collect(),
+            None => Err(invalid(String::from("track_paths must be a list"))),
+        },
+    }
+}
+
+/// The track paths of the settings, which `track_paths` has checked are a list if they are set
+fn track_paths_array(settings: &mut dyn TableLike) -> &mut Array {
+    settings
+        .entry("track_paths")
+        .or_insert(Item::Value(Value::Array(Array::new())))
+        .as_array_mut()
+        .expect("track_paths is a list")
+}
+
+fn parse_value(value: &str) -> Value {
+    value
+        .parse::<Value>()
+        .unwrap_or_else(|_| Value::from(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{get_config_with, ConfigOverrides, Configuration};
+    use tempfile::tempdir;
+    use tempfile::TempDir;
+
+    #[test]
+    fn add_and_remove_track_paths() {
+        let dir = tempdir().unwrap();
+        let config = get_temp_config(&dir, None);
+        let tracker = TimeTracker::new(&config);
+        let notes = dir.path().join("Notes");
+        fs::create_dir(&notes).unwrap();
+
+        tracker.add_track_path(&notes).unwrap();
+
+        let config = config.reload().unwrap();
+        assert_eq!(
+            vec![
+                normalize_path(dir.path().join("Projects")),
+                normalize_path(&notes)
+            ],
+            config.track_paths
+        );
+
+        tracker
+            .remove_track_path(dir.path().join("Projects"))
+            .unwrap();
+
+        let config = config.reload().unwrap();
+        assert_eq!(vec![normalize_path(&notes)], config.track_paths);
+        assert!(tracker
+            .remove_track_path(dir.path().join("Projects"))
+            .is_err());
+    }
+
+    #[test]
+    fn add_invalid_track_paths() {
+        let dir = tempdir().unwrap();
+        let config = get_temp_config(&dir, None);
+        let tracker = TimeTracker::new(&config);
+        let nested = dir.path().join("Projects/timetrack");
+        fs::create_dir(&nested).unwrap();
+
+        // missing, already tracked, nested within and containing a track path
+        assert!(tracker.add_track_path(dir.path().join("Missing")).is_err());
+        assert!(tracker.add_track_path(dir.path().join("Projects")).is_err());
+        assert!(tracker.add_track_path(&nested).is_err());
+        assert!(tracker.add_track_path(dir.path()).is_err());
+
+        assert_eq!(config.track_paths, config.reload().unwrap().track_paths);
+    }
+
+    #[test]
+    fn add_track_path_of_other_profile() {
+        let dir = tempdir().unwrap();
+        let config = get_temp_config(&dir, None);
+        let nested = dir.path().join("Clients/acme");
+        fs::create_dir_all(&nested).unwrap();
+
+        match TimeTracker::new(&config).add_track_path(&nested) {
+            Err(TimeTrackerError::InvalidConfigError(message)) => {
+                assert!(message.contains("overlap"))
+            }
+            _ => panic!("Expected the track paths of the work profile to overlap"),
+        }
+        assert_eq!(config.track_paths, config.reload().unwrap().track_paths);
+    }
+
+    #[test]
+    fn set_settings() {
+        let dir = tempdir().unwrap();
+        let config = get_temp_config(&dir, None);
+        let tracker = TimeTracker::new(&config);
+
+        tracker.set_config("dir_depth", "2").unwrap();
+        tracker.set_config("watcher", "poll").unwrap();
+        tracker
+            .set_config("ignore", r#"["node_modules", "vendor"]"#)
+            .unwrap();
+
+        let config = config.reload().unwrap();
+        assert_eq!(2, config.dir_depth);
+        assert_eq!(crate::watcher::WatcherBackend::Poll, config.watcher);
+        assert_eq!(
+            crate::config::ignore::IgnoreRules::new(&["node_modules", "vendor"]),
+            config.ignore
+        );
+
+        assert!(tracker.set_config("dir_depth", "deep").is_err());
+        assert!(tracker.set_config("watcher", "inotify").is_err());
+        assert!(tracker.set_config("track_paths", "[]").is_err());
+        assert_eq!(2, config.reload().unwrap().dir_depth);
+    }
+
+    #[test]
+    fn set_invalid_working_hours() {
+        let dir = tempdir().unwrap();
+        let config = get_temp_config(&dir, None);
+        let contents = fs::read_to_string(&config.user_config_path).unwrap();
+
+        // parses as a working_hours table, but the range ends before it starts
+        assert!(TimeTracker::new(&config)
+            .set_config("working_hours", r#"{monday=["18:00-09:00"]}"#)
+            .is_err());
+
+        assert_eq!(
+            contents,
+            fs::read_to_string(&config.user_config_path).unwrap()
+        );
+        assert!(config.reload().is_ok());
+    }
+
+    #[test]
+    fn comments_and_formatting_are_kept() {
+        let dir = tempdir().unwrap();
+        let config = get_temp_config(&dir, None);
+        let contents = fs::read_to_string(&config.user_config_path).unwrap();
+        let contents = format!("# tracked by every profile\n{}", contents).replace(
+            "\n\n[profiles.work]\n",
+            "\ndir_depth = 1 # one level\n\n[profiles.work]\n",
+        );
+        fs::write(&config.user_config_path, &contents).unwrap();
+
+        TimeTracker::new(&config)
+            .set_config("dir_depth", "3")
+            .unwrap();
+
+        assert_eq!(
+            contents.replace("dir_depth = 1 ", "dir_depth = 3 "),
+            fs::read_to_string(&config.user_config_path).unwrap()
+        );
+        assert_eq!(3, config.reload().unwrap().dir_depth);
+    }
+
+    #[test]
+    fn unknown_keys_are_kept() {
+        let dir = tempdir().unwrap();
+        let config = get_temp_config(&dir, None);
+        let contents = fs::read_to_string(&config.user_config_path).unwrap();
+        fs::write(
+            &config.user_config_path,
+            format!("future_setting = true\n{}", contents),
+        )
+        .unwrap();
+
+        TimeTracker::new(&config)
+            .set_config("dir_depth", "3")
+            .unwrap();
+
+        let document = read_document(&config.user_config_path).unwrap();
+        assert_eq!(Some(true), document["future_setting"].as_bool());
+        assert_eq!(Some(3), document["dir_depth"].as_integer());
+    }
+
+    #[test]
+    fn edit_profile_settings() {
+        let dir = tempdir().unwrap();
+        let config = get_temp_config(&dir, Some("work"));
+        let tracker = TimeTracker::new(&config);
+
+        tracker.set_config("dir_depth", "2").unwrap();
+
+        let document = read_document(&config.user_config_path).unwrap();
+        assert!(document.get("dir_depth").is_none());
+        assert_eq!(2, config.reload().unwrap().dir_depth);
+    }
+
+    #[test]
+    fn parse_values() {
+        assert_eq!(Some(2), parse_value("2").as_integer());
+        assert_eq!(Some("poll"), parse_value("poll").as_str());
+        assert_eq!(Some("poll"), parse_value(r#""poll""#).as_str());
+        assert_eq!(
+            Some("target"),
+            parse_value(r#"["target"]"#)
+                .as_array()
+                .and_then(|array| array.get(0))
+                .and_then(Value::as_str)
+        );
+    }
+
+    /// A configuration tracking `Projects` in the directory, with a `work` profile tracking `Clients`
+    fn get_temp_config(dir: &TempDir, profile: Option<&str>) -> Configuration {
+        fs::create_dir_all(dir.path().join("Projects")).unwrap();
+        let user_config_path = dir.path().join("timetrack_config");
+        fs::write(
+            &user_config_path,
+            format!(
+                "track_paths = [{:?}]\n\n[profiles.work]\ntrack_paths = [{:?}]\n",
+                normalize_path(dir.path().join("Projects")),
+                normalize_path(dir.path().join("Clients"))
+            ),
+        )
+        .unwrap();
+
+        get_config_with(&ConfigOverrides {
+            config_path: Some(user_config_path),
+            data_dir: Some(dir.path().join("data")),
+            profile: profile.map(String::from),
+        })
+        .unwrap()
+    }
+}
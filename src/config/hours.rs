@@ -0,0 +1,371 @@
+use crate::TimeTrackerError;
+use chrono::{Datelike, Local, NaiveDate, NaiveDateTime, TimeZone, Timelike};
+use serde_derive::{Deserialize, Serialize};
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Formatter;
+
+const WEEKDAYS: [&str; 7] = [
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+];
+
+/// What happens to activity outside of the working hours
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum OffHours {
+    /// The activity isn't stored
+    Drop,
+    /// The activity is stored with an `off-hours` tag
+    Tag,
+}
+
+impl Display for OffHours {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            OffHours::Drop => write!(f, "dropped"),
+            OffHours::Tag => write!(f, "tagged"),
+        }
+    }
+}
+
+/// The `working_hours` table of the user configuration
+///
+/// Each weekday has a list of ranges like `"09:00-17:30"`, and days which aren't listed have no
+/// working hours at all. Holidays are dates like `"2026-12-25"`.
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct WorkingHoursConfig {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    monday: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tuesday: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    wednesday: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    thursday: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    friday: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    saturday: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    sunday: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    holidays: Vec<String>,
+    #[serde(default = "default_off_hours")]
+    off_hours: OffHours,
+}
+
+/// The local times when activity is work, so nightly jobs and backups don't end up on a timesheet
+///
+/// Without a `working_hours` table every time is working time.
+#[derive(Clone, PartialEq, Debug)]
+pub struct WorkingHours {
+    /// The ranges of each weekday, Monday first, as minutes since midnight
+    days: Option<Vec<Vec<(u32, u32)>>>,
+    holidays: Vec<NaiveDate>,
+    pub off_hours: OffHours,
+}
+
+/// The working hours in the layout of `timetrack config --json`
+#[derive(Serialize, PartialEq, Debug)]
+pub struct WorkingHoursSummary {
+    days: Vec<WorkingDay>,
+    holidays: Vec<String>,
+    off_hours: OffHours,
+}
+
+#[derive(Serialize, PartialEq, Debug)]
+struct WorkingDay {
+    day: &'static str,
+    /// Ranges like `"09:00-17:30"`, empty if the day has no working hours
+    ranges: Vec<String>,
+}
+
+impl Default for WorkingHours {
+    fn default() -> Self {
+        WorkingHours {
+            days: None,
+            holidays: vec![],
+            off_hours: default_off_hours(),
+        }
+    }
+}
+
+impl WorkingHours {
+    pub fn new(config: Option<&WorkingHoursConfig>) -> Result<Self, TimeTrackerError> {
+        let config = match config {
+            Some(config) => config,
+            None => return Ok(WorkingHours::default()),
+        };
+
+        let days = [
+            &config.monday,
+            &config.tuesday,
+            &config.wednesday,
+            &config.thursday,
+            &config.friday,
+            &config.saturday,
+            &config.sunday,
+        ]
+        .iter()
+        .zip(WEEKDAYS.iter())
+        .map(|(ranges, weekday)| {
+            ranges
+                .iter()
+                .map(|range| parse_range(range).ok_or_else(|| invalid_range(weekday, range)))
+                .collect()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+        let holidays = config
+            .holidays
+            .iter()
+            .map(|holiday| {
+                NaiveDate::parse_from_str(holiday, "%Y-%m-%d").map_err(|_| {
+                    TimeTrackerError::InvalidConfigError(format!(
+                        "invalid holiday {:?} in working_hours, expected a date like \"2026-12-25\"",
+                        holiday
+                    ))
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(WorkingHours {
+            days: Some(days),
+            holidays,
+            off_hours: config.off_hours,
+        })
+    }
+
+    /// Checks if the timestamp (seconds since the Unix epoch) is within the working hours, in local time
+    pub fn contains(&self, timestamp: u64) -> bool {
+        if self.days.is_none() {
+            return true;
+        }
+
+        match Local.timestamp_opt(timestamp as i64, 0).single() {
+            Some(time) => self.contains_local(time.naive_local()),
+            // timestamps are always valid in local time, which only repeats or skips local times
+            None => true,
+        }
+    }
+
+    /// Summarizes the working hours, or `None` if every time is working time
+    pub fn summary(&self) -> Option<WorkingHoursSummary> {
+        let days = self.days.as_ref()?;
+
+        Some(WorkingHoursSummary {
+            days: WEEKDAYS
+                .iter()
+                .zip(days)
+                .map(|(day, ranges)| WorkingDay {
+                    day,
+                    ranges: ranges.iter().map(|&range| format_range(range)).collect(),
+                })
+                .collect(),
+            holidays: self
+                .holidays
+                .iter()
+                .map(|holiday| holiday.format("%Y-%m-%d").to_string())
+                .collect(),
+            off_hours: self.off_hours,
+        })
+    }
+
+    fn contains_local(&self, time: NaiveDateTime) -> bool {
+        let days = match &self.days {
+            Some(days) => days,
+            None => return true,
+        };
+        if self.holidays.contains(&time.date()) {
+            return false;
+        }
+
+        let minute = time.hour() * 60 + time.minute();
+        days[time.weekday().num_days_from_monday() as usize]
+            .iter()
+            .any(|&(start, end)| start <= minute && minute < end)
+    }
+}
+
+impl Display for WorkingHours {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let days = match &self.days {
+            Some(days) => days,
+            None => return write!(f, "always"),
+        };
+
+        let days: Vec<String> = WEEKDAYS
+            .iter()
+            .zip(days)
+            .filter(|(_, ranges)| !ranges.is_empty())
+            .map(|(weekday, ranges)| {
+                let ranges: Vec<String> = ranges.iter().map(|&range| format_range(range)).collect();
+                format!("{} {}", &weekday[..3], ranges.join(", "))
+            })
+            .collect();
+        write!(
+            f,
+            "{} ({} {}, other activity is {})",
+            days.join("; "),
+            self.holidays.len(),
+            if self.holidays.len() == 1 {
+                "holiday"
+            } else {
+                "holidays"
+            },
+            self.off_hours
+        )
+    }
+}
+
+fn default_off_hours() -> OffHours {
+    OffHours::Drop
+}
+
+/// Parses a range like `09:00-17:30` into minutes since midnight, where the end can be `24:00`
+fn parse_range(range: &str) -> Option<(u32, u32)> {
+    let mut parts = range.splitn(2, '-');
+    let start = parse_minute(parts.next()?)?;
+    let end = parse_minute(parts.next()?)?;
+
+    if start < end {
+        Some((start, end))
+    } else {
+        None
+    }
+}
+
+fn parse_minute(time: &str) -> Option<u32> {
+    let mut parts = time.trim().splitn(2, ':');
+    let hour: u32 = parts.next()?.parse().ok()?;
+    let minute: u32 = parts.next()?.parse().ok()?;
+
+    if minute < 60 && (hour < 24 || (hour == 24 && minute == 0)) {
+        Some(hour * 60 + minute)
+    } else {
+        None
+    }
+}
+
+fn format_range((start, end): (u32, u32)) -> String {
+    format!("{}-{}", format_minute(start), format_minute(end))
+}
+
+fn format_minute(minute: u32) -> String {
+    format!("{:02}:{:02}", minute / 60, minute % 60)
+}
+
+fn invalid_range(weekday: &str, range: &str) -> TimeTrackerError {
+    TimeTrackerError::InvalidConfigError(format!(
+        "invalid range {:?} for {} in working_hours, expected a range like \"09:00-17:30\"",
+        range, weekday
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_working_hours() {
+        let working_hours = WorkingHours::new(None).unwrap();
+
+        assert!(working_hours.contains(0));
+        assert_eq!("always", working_hours.to_string());
+        assert_eq!(None, working_hours.summary());
+    }
+
+    #[test]
+    fn contains_local() {
+        let working_hours = get_working_hours(
+            r#"
+            monday = ["09:00-12:30", "13:30-18:00"]
+            friday = ["09:00-24:00"]
+            holidays = ["2026-12-25"]
+            "#,
+        );
+
+        // 2026-10-19 is a Monday
+        assert!(working_hours.contains_local(local("2026-10-19 09:00")));
+        assert!(working_hours.contains_local(local("2026-10-19 12:29")));
+        assert!(!working_hours.contains_local(local("2026-10-19 12:30")));
+        assert!(!working_hours.contains_local(local("2026-10-19 03:00")));
+        assert!(working_hours.contains_local(local("2026-10-23 23:59")));
+        // weekdays which aren't listed have no working hours
+        assert!(!working_hours.contains_local(local("2026-10-20 10:00")));
+        // 2026-12-25 is a Friday
+        assert!(!working_hours.contains_local(local("2026-12-25 10:00")));
+    }
+
+    #[test]
+    fn display() {
+        let working_hours = get_working_hours(
+            r#"
+            monday = ["09:00-12:30", "13:30-18:00"]
+            friday = ["09:00-24:00"]
+            off_hours = "tag"
+            "#,
+        );
+
+        assert_eq!(
+            "mon 09:00-12:30, 13:30-18:00; fri 09:00-24:00 (0 holidays, other activity is tagged)",
+            working_hours.to_string()
+        );
+    }
+
+    #[test]
+    fn summary() {
+        let working_hours = get_working_hours(
+            r#"
+            monday = ["09:00-12:30", "13:30-18:00"]
+            holidays = ["2026-12-25"]
+            off_hours = "tag"
+            "#,
+        );
+
+        assert_eq!(
+            serde_json::json!({
+                "days": [
+                    { "day": "monday", "ranges": ["09:00-12:30", "13:30-18:00"] },
+                    { "day": "tuesday", "ranges": [] },
+                    { "day": "wednesday", "ranges": [] },
+                    { "day": "thursday", "ranges": [] },
+                    { "day": "friday", "ranges": [] },
+                    { "day": "saturday", "ranges": [] },
+                    { "day": "sunday", "ranges": [] },
+                ],
+                "holidays": ["2026-12-25"],
+                "off_hours": "tag",
+            }),
+            serde_json::to_value(working_hours.summary()).unwrap()
+        );
+    }
+
+    #[test]
+    fn invalid_working_hours() {
+        for contents in &[
+            r#"monday = ["9-17"]"#,
+            r#"monday = ["18:00-09:00"]"#,
+            r#"monday = ["09:00-24:30"]"#,
+            r#"holidays = ["25/12/2026"]"#,
+        ] {
+            let config: WorkingHoursConfig = toml::from_str(contents).unwrap();
+            assert!(WorkingHours::new(Some(&config)).is_err(), "{}", contents);
+        }
+    }
+
+    fn get_working_hours(contents: &str) -> WorkingHours {
+        let config: WorkingHoursConfig = toml::from_str(contents).unwrap();
+        WorkingHours::new(Some(&config)).unwrap()
+    }
+
+    fn local(time: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M").unwrap()
+    }
+}
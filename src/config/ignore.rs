@@ -0,0 +1,103 @@
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Directories within the track paths which are not watched, and whose changes are not tracked
+///
+/// A rule containing a path separator matches that path relative to the track path (for
+/// example `Projects/archive`), any other rule matches a directory with that name at any depth
+/// (for example `node_modules`).
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct IgnoreRules {
+    names: Vec<String>,
+    paths: Vec<PathBuf>,
+}
+
+impl IgnoreRules {
+    pub fn new<I, S>(rules: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut ignore_rules = IgnoreRules::default();
+        for rule in rules {
+            let rule = rule.as_ref().trim_matches('/');
+            if rule.contains('/') {
+                ignore_rules.paths.push(PathBuf::from(rule));
+            } else if !rule.is_empty() {
+                ignore_rules.names.push(rule.to_string());
+            }
+        }
+
+        ignore_rules
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty() && self.paths.is_empty()
+    }
+
+    /// Checks if the path, relative to its track path, is within an ignored directory
+    pub fn is_ignored(&self, relative_path: &Path) -> bool {
+        let matches_name = relative_path.components().any(|component| match component {
+            Component::Normal(name) => self.names.iter().any(|rule| name == rule.as_str()),
+            _ => false,
+        });
+
+        matches_name
+            || self
+                .paths
+                .iter()
+                .any(|rule| relative_path.starts_with(rule))
+    }
+
+    /// The rules as they were configured, names first
+    pub fn rules(&self) -> Vec<String> {
+        self.names
+            .iter()
+            .cloned()
+            .chain(
+                self.paths
+                    .iter()
+                    .map(|path| path.to_string_lossy().to_string()),
+            )
+            .collect()
+    }
+}
+
+impl Display for IgnoreRules {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.rules())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_ignored_by_name() {
+        let ignore_rules = IgnoreRules::new(&["node_modules", "target"]);
+
+        assert!(ignore_rules.is_ignored(Path::new("testProj/target")));
+        assert!(ignore_rules.is_ignored(Path::new("testProj/web/node_modules/left-pad/index.js")));
+        assert!(!ignore_rules.is_ignored(Path::new("testProj/src/target.rs")));
+    }
+
+    #[test]
+    fn is_ignored_by_path() {
+        let ignore_rules = IgnoreRules::new(&["archive/old/"]);
+
+        assert!(ignore_rules.is_ignored(Path::new("archive/old/src/main.rs")));
+        assert!(!ignore_rules.is_ignored(Path::new("archive/new/src/main.rs")));
+        assert!(!ignore_rules.is_ignored(Path::new("testProj/archive/old")));
+    }
+
+    #[test]
+    fn empty() {
+        assert!(IgnoreRules::new(&[""]).is_empty());
+        assert!(!IgnoreRules::new(&["target"]).is_empty());
+    }
+}
@@ -1,25 +1,52 @@
-use crate::watcher;
-use crate::TimeTracker;
+use self::check::describe_toml_error;
+use self::hours::{WorkingHours, WorkingHoursConfig};
+use self::ignore::IgnoreRules;
+use self::path::normalize_path;
+use crate::watcher::{WatcherBackend, WatcherOptions};
+use crate::TimeTrackerError;
 use directories::BaseDirs;
 use directories::ProjectDirs;
 use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
 use std::fmt;
 use std::fmt::Display;
 use std::fmt::Formatter;
 use std::fs;
 use std::fs::OpenOptions;
-use std::io::Read;
 use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
-use std::sync::mpsc::channel;
-use toml;
+use std::time::Duration;
 
+pub mod check;
+mod edit;
+pub mod hours;
+pub mod ignore;
+pub mod path;
+mod self_test;
+
+pub use self::self_test::ConfigFormat;
+
+#[derive(Clone)]
 pub struct Configuration {
     user_config_path: PathBuf, // this file should not be read outside this module
     pub track_paths: Vec<PathBuf>,
     pub raw_data_path: PathBuf,
     pub processed_data_path: PathBuf,
+    pub dir_depth: usize,
+    pub watcher: WatcherBackend,
+    pub poll_interval: Duration,
+    pub ignore: IgnoreRules,
+    pub batch_window: Duration,
+    pub heartbeat_port: Option<u16>,
+    pub working_hours: WorkingHours,
+    /// The data directory shared by every profile
+    data_dir: PathBuf,
+    /// The selected profile, or `None` for the default profile
+    pub profile: Option<String>,
+    /// Every named profile in the user configuration
+    pub profile_names: Vec<String>,
 }
 
 impl Display for Configuration {
@@ -27,14 +54,32 @@ impl Display for Configuration {
         write!(f,
 // Caution: The indent level below matters
 "TimeTrack Configuration
+    Profile: {}
     User configuration: {:?}
     Tracking paths: {:?}
     Raw data: {:?}
-    Processed data: {:?}",
+    Processed data: {:?}
+    Directory depth: {}
+    Watcher: {} (poll interval {:?})
+    Ignored directories: {}
+    Batch window: {:?}
+    Heartbeat API: {}
+    Working hours: {}",
+            self.profile.as_deref().unwrap_or("default"),
             self.user_config_path,
             self.track_paths,
             self.raw_data_path,
-            self.processed_data_path
+            self.processed_data_path,
+            self.dir_depth,
+            self.watcher,
+            self.poll_interval,
+            self.ignore,
+            self.batch_window,
+            match self.heartbeat_port {
+                Some(port) => format!("http://127.0.0.1:{}", port),
+                None => String::from("disabled"),
+            },
+            self.working_hours
         )
     }
 }
@@ -51,89 +96,380 @@ impl Configuration {
             track_paths,
             raw_data_path,
             processed_data_path,
+            dir_depth: default_dir_depth(),
+            watcher: WatcherBackend::Native,
+            poll_interval: Duration::from_secs(default_poll_interval_secs()),
+            ignore: IgnoreRules::new(default_ignore()),
+            batch_window: Duration::from_millis(default_batch_window_ms()),
+            heartbeat_port: None,
+            working_hours: WorkingHours::default(),
+            data_dir: PathBuf::new(),
+            profile: None,
+            profile_names: vec![],
+        }
+    }
+
+    pub fn watcher_options(&self) -> WatcherOptions {
+        WatcherOptions {
+            backend: self.watcher,
+            poll_interval: self.poll_interval,
+            ignore: self.ignore.clone(),
         }
     }
+
+    /// Reads the user configuration file again, keeping the current profile and data paths
+    pub fn reload(&self) -> Result<Configuration, TimeTrackerError> {
+        let user_config = try_read_user_config(&self.user_config_path)?;
+
+        build_configuration(
+            self.user_config_path.clone(),
+            self.data_dir.clone(),
+            self.raw_data_path.clone(),
+            self.processed_data_path.clone(),
+            user_config,
+            self.profile.clone(),
+        )
+    }
+
+    /// The configuration of another profile, from the same user configuration and data directory
+    pub fn for_profile(&self, profile: Option<&str>) -> Result<Configuration, TimeTrackerError> {
+        let user_config = try_read_user_config(&self.user_config_path)?;
+
+        load_profile(
+            self.user_config_path.clone(),
+            self.data_dir.clone(),
+            user_config,
+            profile.map(String::from),
+        )
+    }
+
+    /// The directory containing the user configuration file
+    pub fn user_config_dir(&self) -> Option<&Path> {
+        self.user_config_path.parent()
+    }
+
+    /// The socket the running tracker listens on for commands like `timetrack pause`
+    pub fn control_socket_path(&self) -> PathBuf {
+        self.raw_data_path.with_file_name(".timetrack_control")
+    }
+
+    /// The file holding the process id of the running tracker, see `InstanceLock`
+    pub fn lock_file_path(&self) -> PathBuf {
+        self.raw_data_path.with_file_name(".timetrack_lock")
+    }
+
+    /// Checks if the path is one of the files TimeTrack writes while tracking
+    pub fn is_data_file(&self, path: &Path) -> bool {
+        path == self.raw_data_path
+            || path == self.processed_data_path
+            || path == self.lock_file_path()
+            || path == self.control_socket_path()
+            || path == self.schedule_file_path()
+    }
+
+    /// The command TimeTrack was scheduled with, see `ScheduledCommand`
+    pub fn schedule_file_path(&self) -> PathBuf {
+        self.raw_data_path.with_file_name(".timetrack_schedule")
+    }
+
+    pub fn is_user_config_file(&self, path: &Path) -> bool {
+        path == self.user_config_path
+    }
 }
 
 #[derive(Deserialize, Serialize)]
 struct UserConfig {
     track_paths: Vec<PathBuf>,
+    /// The number of directories below the project recorded with each event
+    #[serde(default = "default_dir_depth")]
+    dir_depth: usize,
+    /// Use `poll` for network file systems, or where native watches are not available
+    #[serde(default = "default_watcher")]
+    watcher: WatcherBackend,
+    #[serde(default = "default_poll_interval_secs")]
+    poll_interval_secs: u64,
+    /// Directories which are neither watched nor tracked, see `IgnoreRules`
+    ///
+    /// New configuration files ignore `default_ignore`, but files written before this setting
+    /// existed keep tracking every directory.
+    #[serde(default)]
+    ignore: Vec<String>,
+    /// How long to wait for more events after the first one, so a burst of changes is stored together
+    #[serde(default = "default_batch_window_ms")]
+    batch_window_ms: u64,
+    /// The localhost port accepting heartbeats from editor plugins, which is disabled if not set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    heartbeat_port: Option<u16>,
+    /// When activity counts as work, see `WorkingHours`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    working_hours: Option<WorkingHoursConfig>,
+    /// Named profiles, each tracking its own paths into separate data files
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    profiles: BTreeMap<String, ProfileConfig>,
+}
+
+/// A named profile, such as work or personal, selected with `--profile`
+///
+/// Settings which aren't given are taken from the top level of the user configuration, except the
+/// heartbeat port, since two profiles can't listen on the same port.
+#[derive(Deserialize, Serialize)]
+struct ProfileConfig {
+    track_paths: Vec<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dir_depth: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    watcher: Option<WatcherBackend>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    poll_interval_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ignore: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    batch_window_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    heartbeat_port: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    working_hours: Option<WorkingHoursConfig>,
+}
+
+impl UserConfig {
+    /// The settings of the named profile, or the top level settings for the default profile
+    fn for_profile(mut self, profile: Option<&str>) -> Result<UserConfig, TimeTrackerError> {
+        let name = match profile {
+            Some(name) => name,
+            None => return Ok(self),
+        };
+        if !is_valid_profile_name(name) {
+            return Err(TimeTrackerError::InvalidProfileError(name.to_string()));
+        }
+        let profile = self
+            .profiles
+            .remove(name)
+            .ok_or_else(|| TimeTrackerError::UnknownProfileError(name.to_string()))?;
+
+        Ok(UserConfig {
+            track_paths: profile.track_paths,
+            dir_depth: profile.dir_depth.unwrap_or(self.dir_depth),
+            watcher: profile.watcher.unwrap_or(self.watcher),
+            poll_interval_secs: profile
+                .poll_interval_secs
+                .unwrap_or(self.poll_interval_secs),
+            ignore: profile.ignore.unwrap_or(self.ignore),
+            batch_window_ms: profile.batch_window_ms.unwrap_or(self.batch_window_ms),
+            heartbeat_port: profile.heartbeat_port,
+            working_hours: profile.working_hours.or(self.working_hours),
+            profiles: self.profiles,
+        })
+    }
+}
+
+/// Profile names are used as directory names for their data files
+fn is_valid_profile_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn default_dir_depth() -> usize {
+    1
+}
+
+fn default_watcher() -> WatcherBackend {
+    WatcherBackend::Native
+}
+
+fn default_poll_interval_secs() -> u64 {
+    5
+}
+
+fn default_ignore() -> Vec<String> {
+    vec![String::from("node_modules"), String::from("target")]
+}
+
+fn default_batch_window_ms() -> u64 {
+    2000
+}
+
+/// Environment variable for the configuration file, overridden by `--config`
+pub const CONFIG_ENV_VAR: &str = "TIMETRACK_CONFIG";
+/// Environment variable for the directory holding the data files, overridden by `--data-dir`
+pub const DATA_DIR_ENV_VAR: &str = "TIMETRACK_DATA_DIR";
+
+/// Where to keep the configuration and data files instead of the usual directories, which allows
+/// running separate profiles side by side
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConfigOverrides {
+    pub config_path: Option<PathBuf>,
+    pub data_dir: Option<PathBuf>,
+    pub profile: Option<String>,
+}
+
+impl ConfigOverrides {
+    /// Fills in locations which weren't given from the environment, and makes them absolute
+    pub fn resolve(self) -> Self {
+        ConfigOverrides {
+            config_path: resolve_override(self.config_path, CONFIG_ENV_VAR),
+            data_dir: resolve_override(self.data_dir, DATA_DIR_ENV_VAR),
+            profile: self.profile,
+        }
+    }
+
+    /// The arguments which give another TimeTrack command the same overrides
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = vec![];
+        if let Some(config_path) = &self.config_path {
+            args.push(String::from("--config"));
+            args.push(config_path.to_string_lossy().to_string());
+        }
+        if let Some(data_dir) = &self.data_dir {
+            args.push(String::from("--data-dir"));
+            args.push(data_dir.to_string_lossy().to_string());
+        }
+        if let Some(profile) = &self.profile {
+            args.push(String::from("--profile"));
+            args.push(profile.clone());
+        }
+        args
+    }
+}
+
+fn resolve_override(path: Option<PathBuf>, env_var: &str) -> Option<PathBuf> {
+    let path = path.or_else(|| {
+        env::var_os(env_var)
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+    })?;
+    let current_dir = env::current_dir().expect("Unable to find the current directory");
+    Some(normalize_path(current_dir.join(path)))
 }
 
 pub fn get_config() -> Configuration {
+    get_config_with(&ConfigOverrides::default()).expect("Failed to read the configuration")
+}
+
+/// Reads the configuration of the selected profile, honoring the overrides and their environment
+/// variables
+pub fn get_config_with(overrides: &ConfigOverrides) -> Result<Configuration, TimeTrackerError> {
+    let user_config_path = get_user_config_path(overrides);
+    let overrides = overrides.clone().resolve();
     let project_dir = ProjectDirs::from("rust", "cargo", "timetrack")
         .expect("Failed to read project directories");
 
-    let raw_data_path = get_data_file_path(&project_dir, ".timetrack_raw");
-    let processed_data_path = get_data_file_path(&project_dir, ".timetrack_processed");
-    let user_config_path = project_dir.config_dir().join("timetrack_config");
-    let user_config = read_user_config(&user_config_path);
+    let data_dir = overrides
+        .data_dir
+        .unwrap_or_else(|| project_dir.data_local_dir().to_path_buf());
+    let user_config = read_user_config(&user_config_path)?;
 
-    Configuration {
+    load_profile(
+        normalize_path(user_config_path),
+        normalize_path(data_dir),
+        user_config,
+        overrides.profile,
+    )
+}
+
+/// The user configuration file selected by the overrides, or the default one
+pub fn get_user_config_path(overrides: &ConfigOverrides) -> PathBuf {
+    overrides.clone().resolve().config_path.unwrap_or_else(|| {
+        ProjectDirs::from("rust", "cargo", "timetrack")
+            .expect("Failed to read project directories")
+            .config_dir()
+            .join("timetrack_config")
+    })
+}
+
+/// Builds the configuration of the profile, creating its data files
+///
+/// Named profiles keep their data files in `profiles/<name>` within the data directory.
+fn load_profile(
+    user_config_path: PathBuf,
+    data_dir: PathBuf,
+    user_config: UserConfig,
+    profile: Option<String>,
+) -> Result<Configuration, TimeTrackerError> {
+    let profile_data_dir = match &profile {
+        Some(name) => data_dir.join("profiles").join(name),
+        None => data_dir.clone(),
+    };
+    let config = build_configuration(
+        user_config_path,
+        data_dir,
+        profile_data_dir.join(".timetrack_raw"),
+        profile_data_dir.join(".timetrack_processed"),
+        user_config,
+        profile,
+    )?;
+    // only created once the profile is known to exist
+    create_data_file(&config.raw_data_path);
+    create_data_file(&config.processed_data_path);
+
+    Ok(config)
+}
+
+fn build_configuration(
+    user_config_path: PathBuf,
+    data_dir: PathBuf,
+    raw_data_path: PathBuf,
+    processed_data_path: PathBuf,
+    user_config: UserConfig,
+    profile: Option<String>,
+) -> Result<Configuration, TimeTrackerError> {
+    let profile_names = user_config.profiles.keys().cloned().collect();
+    let user_config = user_config.for_profile(profile.as_deref())?;
+
+    Ok(Configuration {
         user_config_path,
         // TODO how to handle two track paths where one is a subdirectory of another
-        track_paths: user_config.track_paths,
+        // track paths are normalized so they match the paths reported by the file system watcher
+        track_paths: user_config.track_paths.iter().map(normalize_path).collect(),
         raw_data_path,
         processed_data_path,
-    }
+        dir_depth: user_config.dir_depth,
+        watcher: user_config.watcher,
+        poll_interval: Duration::from_secs(user_config.poll_interval_secs),
+        ignore: IgnoreRules::new(&user_config.ignore),
+        batch_window: Duration::from_millis(user_config.batch_window_ms),
+        heartbeat_port: user_config.heartbeat_port,
+        working_hours: WorkingHours::new(user_config.working_hours.as_ref())?,
+        data_dir,
+        profile,
+        profile_names,
+    })
 }
 
-impl<'a> TimeTracker<'a> {
-    pub fn print_config(&self) {
-        println!("{}", self.config);
-        println!("Starting self test..");
-        let (tx, _rx) = channel();
-        for track_path in &self.config.track_paths {
-            match watcher::get_watcher(track_path, tx.clone()) {
-                Ok(_) => {
-                    println!(
-                        "Successfully added watcher for path {}",
-                        track_path.to_string_lossy()
-                    );
-                }
-                Err(err) => {
-                    println!(
-                        "Error {} adding watcher for path {:?}",
-                        err,
-                        track_path.to_string_lossy()
-                    );
-                }
-            };
-        }
-        println!("Completed self test.");
+fn create_data_file(data_file_path: &Path) {
+    if let Some(data_directory) = data_file_path.parent() {
+        fs::create_dir_all(data_directory).expect("Failed to create data directory");
     }
-}
-
-fn get_data_file_path(project_dirs: &ProjectDirs, filename: &str) -> PathBuf {
-    let data_directory = project_dirs.data_local_dir();
-    let data_file_path = data_directory.join(filename);
-
-    fs::create_dir_all(&data_directory).expect("Failed to create data directory");
     OpenOptions::new()
         .create(true)
         .read(true)
         .write(true)
-        .open(&data_file_path)
+        .open(data_file_path)
         .expect("Failed to create data file");
-
-    data_file_path
 }
 
-fn read_user_config(user_config_path: &PathBuf) -> UserConfig {
+/// Reads the user configuration file, creating it first if it doesn't exist
+fn read_user_config(user_config_path: &PathBuf) -> Result<UserConfig, TimeTrackerError> {
     if !user_config_path.exists() {
         init_config_file(&user_config_path);
     }
 
-    let mut f = OpenOptions::new()
-        .read(true)
-        .open(&user_config_path)
-        .expect("Failed to open config file");
+    try_read_user_config(user_config_path)
+}
 
-    let mut contents = String::new();
-    f.read_to_string(&mut contents)
-        .expect("something went wrong reading the file");
+/// Reads the user configuration file, returning an error rather than panicking if it is invalid
+fn try_read_user_config(user_config_path: &PathBuf) -> Result<UserConfig, TimeTrackerError> {
+    let contents = fs::read_to_string(user_config_path)
+        .map_err(|err| TimeTrackerError::InvalidConfigError(err.to_string()))?;
 
-    toml::from_str(&contents).expect("Failed to parse config file as TOML")
+    toml::from_str(&contents).map_err(|err| {
+        TimeTrackerError::InvalidConfigError(format!(
+            "{}: {}",
+            user_config_path.display(),
+            describe_toml_error(&err, &contents)
+        ))
+    })
 }
 
 fn init_config_file(config_file_path: impl AsRef<Path>) {
@@ -153,6 +489,14 @@ fn init_config_file(config_file_path: impl AsRef<Path>) {
         .to_owned();
     let default_config = UserConfig {
         track_paths: vec![home_dir],
+        dir_depth: default_dir_depth(),
+        watcher: default_watcher(),
+        poll_interval_secs: default_poll_interval_secs(),
+        ignore: default_ignore(),
+        batch_window_ms: default_batch_window_ms(),
+        heartbeat_port: None,
+        working_hours: None,
+        profiles: BTreeMap::new(),
     };
 
     write!(
@@ -162,3 +506,284 @@ fn init_config_file(config_file_path: impl AsRef<Path>) {
     )
     .expect("Failed to initialize configuration file");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn get_config_with_overrides() {
+        let dir = tempdir().unwrap();
+        let user_config_path = dir.path().join("profile/timetrack_config");
+        fs::create_dir(dir.path().join("profile")).unwrap();
+        fs::write(
+            &user_config_path,
+            r#"track_paths = ["/Users/josh/Projects"]"#,
+        )
+        .unwrap();
+        let overrides = ConfigOverrides {
+            config_path: Some(user_config_path.clone()),
+            data_dir: Some(dir.path().join("data")),
+            profile: None,
+        };
+
+        let config = get_config_with(&overrides).unwrap();
+
+        let dir = normalize_path(dir.path());
+        assert!(config.is_user_config_file(&dir.join("profile/timetrack_config")));
+        assert_eq!(
+            vec![PathBuf::from("/Users/josh/Projects")],
+            config.track_paths
+        );
+        assert_eq!(dir.join("data/.timetrack_raw"), config.raw_data_path);
+        assert_eq!(
+            dir.join("data/.timetrack_processed"),
+            config.processed_data_path
+        );
+        assert!(config.raw_data_path.exists());
+    }
+
+    #[test]
+    fn resolve_overrides() {
+        env::set_var("TIMETRACK_TEST_DATA_DIR", "/Users/josh/timetrack");
+
+        assert_eq!(
+            Some(PathBuf::from("/Users/josh/other")),
+            resolve_override(
+                Some(PathBuf::from("/Users/josh/other")),
+                "TIMETRACK_TEST_DATA_DIR"
+            )
+        );
+        assert_eq!(
+            Some(PathBuf::from("/Users/josh/timetrack")),
+            resolve_override(None, "TIMETRACK_TEST_DATA_DIR")
+        );
+        assert_eq!(
+            Some(normalize_path(env::current_dir().unwrap()).join("profile")),
+            resolve_override(Some(PathBuf::from("profile")), "TIMETRACK_TEST_CONFIG")
+        );
+        assert_eq!(None, resolve_override(None, "TIMETRACK_TEST_CONFIG"));
+    }
+
+    #[test]
+    fn overrides_to_args() {
+        let overrides = ConfigOverrides {
+            config_path: Some(PathBuf::from("/Users/josh/client/timetrack_config")),
+            data_dir: None,
+            profile: Some(String::from("work")),
+        };
+
+        assert_eq!(
+            vec![
+                "--config",
+                "/Users/josh/client/timetrack_config",
+                "--profile",
+                "work"
+            ],
+            overrides.to_args()
+        );
+    }
+
+    #[test]
+    fn user_config_defaults() {
+        let user_config: UserConfig =
+            toml::from_str(r#"track_paths = ["/Users/josh/Projects"]"#).unwrap();
+
+        assert_eq!(1, user_config.dir_depth);
+        assert_eq!(WatcherBackend::Native, user_config.watcher);
+        assert_eq!(5, user_config.poll_interval_secs);
+        assert!(user_config.ignore.is_empty());
+        assert_eq!(2000, user_config.batch_window_ms);
+        assert_eq!(None, user_config.heartbeat_port);
+    }
+
+    #[test]
+    fn profile_settings() {
+        let user_config: UserConfig = toml::from_str(
+            r#"track_paths = ["/Users/josh/Projects"]
+dir_depth = 2
+heartbeat_port = 9123
+
+[profiles.work]
+track_paths = ["/Users/josh/Clients"]
+ignore = ["vendor"]
+
+[profiles.oss]
+track_paths = ["/Users/josh/OpenSource"]"#,
+        )
+        .unwrap();
+
+        let config = build_configuration(
+            PathBuf::from("/Users/josh/.config/timetrack_config"),
+            PathBuf::from("/Users/josh"),
+            PathBuf::from("/Users/josh/profiles/work/.timetrack_raw"),
+            PathBuf::from("/Users/josh/profiles/work/.timetrack_processed"),
+            user_config,
+            Some(String::from("work")),
+        )
+        .unwrap();
+
+        assert_eq!(Some(String::from("work")), config.profile);
+        assert_eq!(vec!["oss", "work"], config.profile_names);
+        assert_eq!(
+            vec![PathBuf::from("/Users/josh/Clients")],
+            config.track_paths
+        );
+        assert_eq!(IgnoreRules::new(&["vendor"]), config.ignore);
+        // taken from the top level
+        assert_eq!(2, config.dir_depth);
+        // not shared, since profiles can't listen on the same port
+        assert_eq!(None, config.heartbeat_port);
+    }
+
+    #[test]
+    fn unknown_and_invalid_profiles() {
+        let user_config = || -> UserConfig {
+            toml::from_str(
+                r#"track_paths = ["/Users/josh/Projects"]
+
+[profiles.work]
+track_paths = ["/Users/josh/Clients"]"#,
+            )
+            .unwrap()
+        };
+
+        match user_config().for_profile(Some("personal")) {
+            Err(TimeTrackerError::UnknownProfileError(name)) => assert_eq!("personal", name),
+            _ => panic!("The profile should be unknown"),
+        }
+        match user_config().for_profile(Some("../work")) {
+            Err(TimeTrackerError::InvalidProfileError(name)) => assert_eq!("../work", name),
+            _ => panic!("The profile name should be invalid"),
+        }
+        assert!(user_config().for_profile(None).is_ok());
+    }
+
+    #[test]
+    fn get_config_with_profile() {
+        let dir = tempdir().unwrap();
+        let user_config_path = dir.path().join("timetrack_config");
+        fs::write(
+            &user_config_path,
+            r#"track_paths = ["/Users/josh/Projects"]
+
+[profiles.work]
+track_paths = ["/Users/josh/Clients"]"#,
+        )
+        .unwrap();
+        let overrides = ConfigOverrides {
+            config_path: Some(user_config_path),
+            data_dir: Some(dir.path().join("data")),
+            profile: Some(String::from("work")),
+        };
+
+        let config = get_config_with(&overrides).unwrap();
+
+        assert_eq!(
+            normalize_path(dir.path()).join("data/profiles/work/.timetrack_raw"),
+            config.raw_data_path
+        );
+        assert!(config.raw_data_path.exists());
+
+        let default_config = get_config_with(&ConfigOverrides {
+            profile: None,
+            ..overrides.clone()
+        })
+        .unwrap();
+
+        assert_eq!(vec!["work"], default_config.profile_names);
+        assert_eq!(
+            config.raw_data_path,
+            default_config
+                .for_profile(Some("work"))
+                .unwrap()
+                .raw_data_path
+        );
+
+        let overrides = ConfigOverrides {
+            profile: Some(String::from("personal")),
+            ..overrides
+        };
+
+        assert!(get_config_with(&overrides).is_err());
+        assert!(!dir.path().join("data/profiles/personal").exists());
+    }
+
+    #[test]
+    fn reload() {
+        let dir = tempdir().unwrap();
+        let user_config_path = dir.path().join("timetrack_config");
+        fs::write(
+            &user_config_path,
+            r#"track_paths = ["/Users/josh/Projects"]"#,
+        )
+        .unwrap();
+        let config = build_configuration(
+            user_config_path.clone(),
+            PathBuf::from("/Users/josh"),
+            PathBuf::from("/Users/josh/.timetrack_raw"),
+            PathBuf::from("/Users/josh/.timetrack_processed"),
+            try_read_user_config(&user_config_path).unwrap(),
+            None,
+        )
+        .unwrap();
+
+        fs::write(
+            &user_config_path,
+            r#"track_paths = ["/Users/josh/OtherProjects"]
+dir_depth = 2"#,
+        )
+        .unwrap();
+        let reloaded_config = config.reload().unwrap();
+
+        assert_eq!(
+            vec![PathBuf::from("/Users/josh/OtherProjects")],
+            reloaded_config.track_paths
+        );
+        assert_eq!(2, reloaded_config.dir_depth);
+        assert_eq!(config.raw_data_path, reloaded_config.raw_data_path);
+    }
+
+    #[test]
+    fn reload_invalid() {
+        let dir = tempdir().unwrap();
+        let user_config_path = dir.path().join("timetrack_config");
+        fs::write(
+            &user_config_path,
+            r#"track_paths = ["/Users/josh/Projects"]"#,
+        )
+        .unwrap();
+        let config = build_configuration(
+            user_config_path.clone(),
+            PathBuf::from("/Users/josh"),
+            PathBuf::from("/Users/josh/.timetrack_raw"),
+            PathBuf::from("/Users/josh/.timetrack_processed"),
+            try_read_user_config(&user_config_path).unwrap(),
+            None,
+        )
+        .unwrap();
+
+        fs::write(&user_config_path, "track_paths = [").unwrap();
+
+        assert!(config.reload().is_err());
+    }
+
+    #[test]
+    fn invalid_config_error_location() {
+        let dir = tempdir().unwrap();
+        let user_config_path = dir.path().join("timetrack_config");
+        fs::write(&user_config_path, "track_paths = []\ndir_depth = \"2\"\n").unwrap();
+
+        match try_read_user_config(&user_config_path) {
+            Err(TimeTrackerError::InvalidConfigError(message)) => assert_eq!(
+                format!(
+                    "{}: line 2, column 13: invalid type: string \"2\", expected usize for key `dir_depth`",
+                    user_config_path.display()
+                ),
+                message
+            ),
+            _ => panic!("Expected an invalid config error"),
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use std::ffi::OsString;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Whether paths which differ only by case refer to the same file on this platform's default file system
+const CASE_INSENSITIVE_FILE_SYSTEM: bool = cfg!(any(target_os = "macos", target_os = "windows"));
+
+/// Resolves symlinks and relative components so that equivalent paths compare equal
+///
+/// Paths which no longer exist (for example, a removed or renamed file) are resolved
+/// through their closest existing ancestor. Paths which can't be resolved are returned as-is.
+pub fn normalize_path<P>(path: P) -> PathBuf
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    if let Ok(canonical_path) = path.canonicalize() {
+        return canonical_path;
+    }
+
+    let mut missing_names: Vec<OsString> = vec![];
+    let mut ancestor = path;
+    while let (Some(parent), Some(name)) = (ancestor.parent(), ancestor.file_name()) {
+        missing_names.push(name.to_owned());
+        ancestor = parent;
+
+        if let Ok(canonical_ancestor) = ancestor.canonicalize() {
+            return missing_names
+                .iter()
+                .rev()
+                .fold(canonical_ancestor, |acc, name| acc.join(name));
+        }
+    }
+
+    path.to_path_buf()
+}
+
+/// Returns the path relative to the prefix, if it is within the prefix
+pub fn strip_path_prefix<P, Q>(path: P, prefix: Q) -> Option<PathBuf>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    strip_path_prefix_with_case(path.as_ref(), prefix.as_ref(), CASE_INSENSITIVE_FILE_SYSTEM)
+}
+
+fn strip_path_prefix_with_case(path: &Path, prefix: &Path, ignore_case: bool) -> Option<PathBuf> {
+    if !ignore_case {
+        return path.strip_prefix(prefix).ok().map(Path::to_path_buf);
+    }
+
+    let mut components = path.components();
+    for prefix_component in prefix.components() {
+        let component = components.next()?;
+        let matches = component.as_os_str().to_string_lossy().to_lowercase()
+            == prefix_component
+                .as_os_str()
+                .to_string_lossy()
+                .to_lowercase();
+        if !matches {
+            return None;
+        }
+    }
+
+    Some(components.as_path().to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn normalize_existing_path() {
+        let dir = tempdir().unwrap();
+        let project_dir = dir.path().join("testProj");
+        fs::create_dir(&project_dir).unwrap();
+
+        assert_eq!(
+            project_dir.canonicalize().unwrap(),
+            normalize_path(project_dir.join("../testProj"))
+        );
+    }
+
+    #[test]
+    fn normalize_removed_path() {
+        let dir = tempdir().unwrap();
+        let removed_file = dir.path().join("testProj/file1.rs");
+
+        assert_eq!(
+            dir.path().canonicalize().unwrap().join("testProj/file1.rs"),
+            normalize_path(&removed_file)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn normalize_symlinked_path() {
+        let dir = tempdir().unwrap();
+        let project_dir = dir.path().join("testProj");
+        let link = dir.path().join("link");
+        fs::create_dir(&project_dir).unwrap();
+        std::os::unix::fs::symlink(&project_dir, &link).unwrap();
+
+        assert_eq!(
+            project_dir.canonicalize().unwrap().join("file1.rs"),
+            normalize_path(link.join("file1.rs"))
+        );
+    }
+
+    #[test]
+    fn strip_path_prefix_case_sensitive() {
+        assert_eq!(
+            Some(PathBuf::from("testProj/file1.rs")),
+            strip_path_prefix_with_case(
+                Path::new("/Users/josh/Projects/testProj/file1.rs"),
+                Path::new("/Users/josh/Projects"),
+                false
+            )
+        );
+        assert_eq!(
+            None,
+            strip_path_prefix_with_case(
+                Path::new("/Users/josh/projects/testProj/file1.rs"),
+                Path::new("/Users/josh/Projects"),
+                false
+            )
+        );
+    }
+
+    #[test]
+    fn strip_path_prefix_case_insensitive() {
+        assert_eq!(
+            Some(PathBuf::from("testProj/file1.rs")),
+            strip_path_prefix_with_case(
+                Path::new("/Users/josh/projects/testProj/file1.rs"),
+                Path::new("/Users/josh/Projects"),
+                true
+            )
+        );
+        assert_eq!(
+            None,
+            strip_path_prefix_with_case(
+                Path::new("/Users/josh/Other/testProj/file1.rs"),
+                Path::new("/Users/josh/Projects"),
+                true
+            )
+        );
+    }
+}
@@ -0,0 +1,307 @@
+use super::hours::WorkingHoursSummary;
+use super::Configuration;
+use crate::lock::running_tracker;
+use crate::watcher;
+use crate::watcher::WatcherBackend;
+use crate::TimeTracker;
+use serde_derive::Serialize;
+use std::fs;
+use std::fs::OpenOptions;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::mpsc::channel;
+
+/// How `timetrack config` displays the configuration
+pub enum ConfigFormat {
+    Text,
+    /// A single JSON object, for scripts and bug reports
+    Json,
+}
+
+/// Everything `timetrack config` displays, in the layout of the JSON format
+#[derive(Serialize)]
+struct ConfigReport<'c> {
+    profile: Option<&'c str>,
+    profiles: &'c [String],
+    paths: ConfigPaths<'c>,
+    settings: ConfigSettings<'c>,
+    self_test: SelfTest,
+}
+
+#[derive(Serialize)]
+struct ConfigPaths<'c> {
+    user_config: &'c Path,
+    data_dir: &'c Path,
+    raw_data: &'c Path,
+    processed_data: &'c Path,
+    lock_file: PathBuf,
+    control_socket: PathBuf,
+    schedule_file: PathBuf,
+}
+
+/// The settings in effect for the selected profile, after applying the defaults
+#[derive(Serialize)]
+struct ConfigSettings<'c> {
+    track_paths: &'c [PathBuf],
+    dir_depth: usize,
+    watcher: WatcherBackend,
+    poll_interval_secs: u64,
+    ignore: Vec<String>,
+    batch_window_ms: u64,
+    heartbeat_port: Option<u16>,
+    /// `None` when every time is working time
+    working_hours: Option<WorkingHoursSummary>,
+}
+
+#[derive(Serialize)]
+struct SelfTest {
+    watchers: Vec<WatcherTest>,
+    data_files: Vec<DataFileTest>,
+    /// The output of `git --version`, or `None` if git can't be run
+    git: Option<String>,
+    /// Only available on Linux
+    inotify: Option<InotifyLimits>,
+    /// The process id of the tracker running with this profile
+    running_tracker: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct WatcherTest {
+    track_path: PathBuf,
+    /// The backend actually used, which falls back to polling when the watch limit is reached
+    backend: Option<WatcherBackend>,
+    watched_dirs: Option<usize>,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct DataFileTest {
+    path: PathBuf,
+    error: Option<String>,
+}
+
+#[derive(Serialize, PartialEq, Debug)]
+struct InotifyLimits {
+    max_user_watches: u64,
+    max_user_instances: u64,
+}
+
+impl<'a> TimeTracker<'a> {
+    pub fn print_config(&self, format: &ConfigFormat) {
+        let config = self.config;
+        let report = ConfigReport {
+            profile: config.profile.as_deref(),
+            profiles: &config.profile_names,
+            paths: ConfigPaths {
+                user_config: &config.user_config_path,
+                data_dir: &config.data_dir,
+                raw_data: &config.raw_data_path,
+                processed_data: &config.processed_data_path,
+                lock_file: config.lock_file_path(),
+                control_socket: config.control_socket_path(),
+                schedule_file: config.schedule_file_path(),
+            },
+            settings: ConfigSettings {
+                track_paths: &config.track_paths,
+                dir_depth: config.dir_depth,
+                watcher: config.watcher,
+                poll_interval_secs: config.poll_interval.as_secs(),
+                ignore: config.ignore.rules(),
+                batch_window_ms: config.batch_window.as_millis() as u64,
+                heartbeat_port: config.heartbeat_port,
+                working_hours: config.working_hours.summary(),
+            },
+            self_test: run_self_test(config),
+        };
+
+        match format {
+            ConfigFormat::Text => {
+                println!("{}", config);
+                print_self_test(&report.self_test);
+            }
+            ConfigFormat::Json => println!(
+                "{}",
+                serde_json::to_string_pretty(&report).expect("The configuration is serializable")
+            ),
+        }
+    }
+}
+
+fn run_self_test(config: &Configuration) -> SelfTest {
+    let (tx, _rx) = channel();
+    let watchers = config
+        .track_paths
+        .iter()
+        .map(|track_path| {
+            match watcher::get_watcher(track_path, tx.clone(), &config.watcher_options()) {
+                Ok(watcher) => WatcherTest {
+                    track_path: track_path.clone(),
+                    backend: Some(watcher.backend()),
+                    watched_dirs: watcher.watched_dir_count(),
+                    error: None,
+                },
+                Err(err) => WatcherTest {
+                    track_path: track_path.clone(),
+                    backend: None,
+                    watched_dirs: None,
+                    error: Some(err.to_string()),
+                },
+            }
+        })
+        .collect();
+
+    let data_files = [&config.raw_data_path, &config.processed_data_path]
+        .iter()
+        .map(|path| DataFileTest {
+            path: path.to_path_buf(),
+            error: check_writable(path).err(),
+        })
+        .collect();
+
+    SelfTest {
+        watchers,
+        data_files,
+        git: git_version(),
+        inotify: read_inotify_limits(Path::new("/proc/sys/fs/inotify")),
+        running_tracker: running_tracker(config),
+    }
+}
+
+fn print_self_test(self_test: &SelfTest) {
+    println!("Starting self test..");
+    for watcher in &self_test.watchers {
+        match (&watcher.backend, &watcher.error) {
+            (Some(backend), _) => {
+                println!(
+                    "Successfully added {} watcher for path {}",
+                    backend,
+                    watcher.track_path.to_string_lossy()
+                );
+                if let Some(count) = watcher.watched_dirs {
+                    println!(
+                        "    Watching {} directories (ignored directories are skipped)",
+                        count
+                    );
+                }
+            }
+            (None, error) => println!(
+                "Error {} adding watcher for path {:?}",
+                error.as_deref().unwrap_or("unknown"),
+                watcher.track_path.to_string_lossy()
+            ),
+        }
+    }
+
+    for data_file in &self_test.data_files {
+        match &data_file.error {
+            None => println!("Data file {:?} is writable", data_file.path),
+            Some(err) => println!("Error {} writing data file {:?}", err, data_file.path),
+        }
+    }
+
+    match &self_test.git {
+        Some(version) => println!("Found {}", version),
+        None => println!("git was not found, so files ignored by .gitignore files are tracked"),
+    }
+
+    if let Some(limits) = &self_test.inotify {
+        let watched_dirs: usize = self_test
+            .watchers
+            .iter()
+            .filter_map(|watcher| watcher.watched_dirs)
+            .sum();
+        println!(
+            "inotify allows {} watches and {} instances per user",
+            limits.max_user_watches, limits.max_user_instances
+        );
+        if watched_dirs as u64 > limits.max_user_watches {
+            println!(
+                "    Watching {} directories needs more watches, see fs.inotify.max_user_watches",
+                watched_dirs
+            );
+        }
+    }
+
+    match self_test.running_tracker {
+        Some(pid) => println!("TimeTrack is running (process {})", pid),
+        None => println!("TimeTrack is not running"),
+    }
+    println!("Completed self test.");
+}
+
+/// Opens the file for appending, which is how the tracker writes it, without changing it
+fn check_writable(path: &Path) -> Result<(), String> {
+    OpenOptions::new()
+        .append(true)
+        .open(path)
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
+
+fn git_version() -> Option<String> {
+    let output = Command::new("git").arg("--version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn read_inotify_limits(dir: &Path) -> Option<InotifyLimits> {
+    let read_limit = |name: &str| -> Option<u64> {
+        fs::read_to_string(dir.join(name)).ok()?.trim().parse().ok()
+    };
+
+    Some(InotifyLimits {
+        max_user_watches: read_limit("max_user_watches")?,
+        max_user_instances: read_limit("max_user_instances")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn self_test() {
+        let dir = tempdir().unwrap();
+        let data_dir = dir.path().join("data");
+        fs::create_dir_all(dir.path().join("Projects")).unwrap();
+        fs::create_dir(&data_dir).unwrap();
+        fs::write(data_dir.join(".timetrack_raw"), "").unwrap();
+        let config = Configuration::new_mock_config(
+            vec![dir.path().join("Projects"), dir.path().join("Missing")],
+            data_dir.join(".timetrack_raw"),
+            data_dir.join(".timetrack_processed"),
+        );
+
+        let self_test = run_self_test(&config);
+
+        assert!(self_test.watchers[0].error.is_none());
+        assert!(self_test.watchers[1].error.is_some());
+        assert!(self_test.data_files[0].error.is_none());
+        // the processed data file doesn't exist, so it isn't opened
+        assert!(self_test.data_files[1].error.is_some());
+        assert_eq!(None, self_test.running_tracker);
+    }
+
+    #[test]
+    fn inotify_limits() {
+        let dir = tempdir().unwrap();
+
+        assert_eq!(None, read_inotify_limits(dir.path()));
+
+        fs::write(dir.path().join("max_user_watches"), "8192\n").unwrap();
+        fs::write(dir.path().join("max_user_instances"), "128\n").unwrap();
+
+        assert_eq!(
+            Some(InotifyLimits {
+                max_user_watches: 8192,
+                max_user_instances: 128,
+            }),
+            read_inotify_limits(dir.path())
+        );
+    }
+}
@@ -0,0 +1,490 @@
+use crate::calc::raw_log::RawLog;
+use crate::calc::to_hms;
+#[cfg(unix)]
+use crate::config::Configuration;
+use crate::track::now;
+use crate::TimeTracker;
+use crate::TimeTrackerError;
+use chrono::{Local, NaiveDate};
+use log::{debug, log};
+#[cfg(unix)]
+use log::{info, warn};
+#[cfg(unix)]
+use signal_hook::iterator::Signals;
+#[cfg(unix)]
+use signal_hook::{SIGHUP, SIGINT, SIGTERM};
+#[cfg(unix)]
+use std::fs;
+#[cfg(unix)]
+use std::io::{BufRead, BufReader, Write};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+#[cfg(unix)]
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long `timetrack stop` waits for the tracker to shut down
+const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+enum Pause {
+    Indefinitely,
+    Until(Instant),
+}
+
+/// The state of the running tracker, which is shared with the control socket
+pub(crate) struct TrackerState {
+    started: Instant,
+    track_paths: Vec<PathBuf>,
+    pause: Option<Pause>,
+    last_log: Option<RawLog>,
+    /// The local day and the number of records stored on it, counted as they are stored since the
+    /// raw data file is compacted by every report
+    events_today: (NaiveDate, usize),
+    stop_requested: bool,
+    reload_requested: bool,
+}
+
+impl TrackerState {
+    pub fn new(track_paths: &[PathBuf]) -> Self {
+        TrackerState {
+            started: Instant::now(),
+            track_paths: track_paths.to_vec(),
+            pause: None,
+            last_log: None,
+            events_today: (Local::now().date_naive(), 0),
+            stop_requested: false,
+            reload_requested: false,
+        }
+    }
+
+    /// Checks if tracking is paused, resuming once a timed pause is over
+    pub fn is_paused(&mut self) -> bool {
+        match self.pause {
+            Some(Pause::Until(until)) if Instant::now() >= until => {
+                self.pause = None;
+                false
+            }
+            Some(_) => true,
+            None => false,
+        }
+    }
+
+    pub fn is_stop_requested(&self) -> bool {
+        self.stop_requested
+    }
+
+    pub fn request_stop(&mut self) {
+        self.stop_requested = true;
+    }
+
+    /// Checks if a reload was requested since the last check
+    pub fn take_reload_request(&mut self) -> bool {
+        std::mem::replace(&mut self.reload_requested, false)
+    }
+
+    pub fn request_reload(&mut self) {
+        self.reload_requested = true;
+    }
+
+    /// Shows the track paths of a reloaded configuration in the status
+    pub fn set_track_paths(&mut self, track_paths: &[PathBuf]) {
+        self.track_paths = track_paths.to_vec();
+    }
+
+    /// Records activity which was stored, so the status can show the current project
+    pub fn record(&mut self, log: RawLog) {
+        let today = Local::now().date_naive();
+        if self.events_today.0 != today {
+            self.events_today = (today, 0);
+        }
+        self.events_today.1 += 1;
+        self.last_log = Some(log);
+    }
+
+    /// The number of records stored since the start of the local day, or since the tracker started
+    fn events_today(&self) -> usize {
+        match self.events_today {
+            (day, count) if day == Local::now().date_naive() => count,
+            _ => 0,
+        }
+    }
+
+    fn pause(&mut self, duration: Option<Duration>) {
+        self.pause = Some(match duration {
+            Some(duration) => Pause::Until(Instant::now() + duration),
+            None => Pause::Indefinitely,
+        });
+    }
+
+    fn resume(&mut self) {
+        self.pause = None;
+    }
+
+    fn status(&mut self) -> String {
+        let now = now();
+        let tracking = if self.is_paused() {
+            match self.pause {
+                Some(Pause::Until(until)) => format!(
+                    "paused for {}",
+                    to_hms((until - Instant::now()).as_secs().max(1))
+                ),
+                _ => String::from("paused until resumed"),
+            }
+        } else {
+            String::from("active")
+        };
+        let current_project = match &self.last_log {
+            Some(log) => format!("{} ({})", log.name, ago(now.saturating_sub(log.timestamp))),
+            None => String::from("none"),
+        };
+
+        format!(
+            "TimeTrack is running (started {})
+Tracking: {}
+Watching: {:?}
+Events today: {}
+Current project: {}
+",
+            ago(self.started.elapsed().as_secs()),
+            tracking,
+            self.track_paths,
+            self.events_today(),
+            current_project
+        )
+    }
+}
+
+/// Listens for commands from `timetrack status`, `pause` and `resume`, and removes the socket when dropped
+#[cfg(unix)]
+pub(crate) struct ControlSocket {
+    path: PathBuf,
+}
+
+#[cfg(unix)]
+impl ControlSocket {
+    pub fn listen(
+        config: &Configuration,
+        state: Arc<Mutex<TrackerState>>,
+    ) -> Result<Self, TimeTrackerError> {
+        let path = config.control_socket_path();
+        if UnixStream::connect(&path).is_ok() {
+            return Err(TimeTrackerError::ControlError(String::from(
+                "another tracker is already listening",
+            )));
+        }
+        // the socket is left behind if a tracker doesn't shut down cleanly
+        let _ = fs::remove_file(&path);
+
+        let listener = UnixListener::bind(&path)
+            .map_err(|err| TimeTrackerError::ControlError(err.to_string()))?;
+        thread::Builder::new()
+            .name(String::from("control socket"))
+            .spawn(move || {
+                for stream in listener.incoming().filter_map(Result::ok) {
+                    if let Err(err) = handle_connection(stream, &state) {
+                        debug!("Failed to handle control command ({})", err);
+                    }
+                }
+            })
+            .expect("Failed to start control socket");
+
+        Ok(ControlSocket { path })
+    }
+}
+
+#[cfg(unix)]
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Stops the tracker on SIGTERM or SIGINT, and reloads the configuration on SIGHUP
+///
+/// The returned handle must be closed once tracking stops, which ends the signal handling thread.
+#[cfg(unix)]
+pub(crate) fn handle_signals(state: Arc<Mutex<TrackerState>>) -> Option<Signals> {
+    let signals = match Signals::new([SIGTERM, SIGINT, SIGHUP]) {
+        Ok(signals) => signals,
+        Err(err) => {
+            warn!("Failed to handle signals ({})", err);
+            return None;
+        }
+    };
+
+    let handle = signals.clone();
+    thread::Builder::new()
+        .name(String::from("signal handler"))
+        .spawn(move || {
+            for signal in signals.forever() {
+                let mut state = state.lock().expect("Failed to lock tracker state");
+                if signal == SIGHUP {
+                    info!("Reloading configuration");
+                    state.request_reload();
+                } else {
+                    info!("Received signal {}, stopping", signal);
+                    state.request_stop();
+                }
+            }
+        })
+        .expect("Failed to start signal handler");
+
+    Some(handle)
+}
+
+#[cfg(unix)]
+fn handle_connection(stream: UnixStream, state: &Mutex<TrackerState>) -> std::io::Result<()> {
+    let mut command = String::new();
+    BufReader::new(&stream).read_line(&mut command)?;
+
+    let response = handle_command(state, command.trim());
+    (&stream).write_all(response.as_bytes())
+}
+
+/// Commands are a single line, `status`, `paused`, `pause`, `pause <seconds>`, `resume` or `stop`
+fn handle_command(state: &Mutex<TrackerState>, command: &str) -> String {
+    let mut state = state.lock().expect("Failed to lock tracker state");
+    let mut parts = command.split_whitespace();
+
+    match (parts.next(), parts.next()) {
+        (Some("status"), None) => state.status(),
+        (Some("paused"), None) => String::from(if state.is_paused() {
+            "paused\n"
+        } else {
+            "active\n"
+        }),
+        (Some("pause"), None) => {
+            state.pause(None);
+            String::from("Tracking paused until resumed\n")
+        }
+        (Some("pause"), Some(seconds)) => match seconds.parse::<u64>() {
+            Ok(seconds) => {
+                state.pause(Some(Duration::from_secs(seconds)));
+                format!("Tracking paused for {}\n", to_hms(seconds))
+            }
+            Err(_) => format!("Invalid pause duration {:?}\n", seconds),
+        },
+        (Some("resume"), None) => {
+            state.resume();
+            String::from("Tracking resumed\n")
+        }
+        (Some("stop"), None) => {
+            state.request_stop();
+            String::from("Stopping TimeTrack\n")
+        }
+        _ => format!("Unknown command {:?}\n", command),
+    }
+}
+
+impl<'a> TimeTracker<'a> {
+    pub fn status(&self) {
+        match self.send_control_command("status") {
+            Ok(response) => print!("{}", response),
+            Err(_) => println!("TimeTrack is not running"),
+        }
+    }
+
+    /// Stops storing activity in the running tracker, until resumed or for a duration like `30m`
+    pub fn pause(&self, duration: Option<&str>) -> Result<(), TimeTrackerError> {
+        let command = match duration {
+            Some(duration) => format!("pause {}", parse_duration(duration)?.as_secs()),
+            None => String::from("pause"),
+        };
+        print!("{}", self.send_control_command(&command)?);
+
+        Ok(())
+    }
+
+    pub fn resume(&self) -> Result<(), TimeTrackerError> {
+        print!("{}", self.send_control_command("resume")?);
+
+        Ok(())
+    }
+
+    /// Checks if the running tracker is paused, so activity reported to it while paused is dropped
+    /// as well, which is never the case when no tracker is running
+    pub(crate) fn is_tracking_paused(&self) -> bool {
+        match self.send_control_command("paused") {
+            Ok(response) => response == "paused\n",
+            Err(_) => false,
+        }
+    }
+
+    /// Asks the running tracker to shut down, and waits for it to release its lock
+    pub fn stop(&self) -> Result<(), TimeTrackerError> {
+        print!("{}", self.send_control_command("stop")?);
+
+        let start = Instant::now();
+        while self.config.lock_file_path().exists() {
+            if start.elapsed() >= STOP_TIMEOUT {
+                return Err(TimeTrackerError::ControlError(String::from(
+                    "TimeTrack did not stop in time",
+                )));
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        println!("TimeTrack stopped");
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    fn send_control_command(&self, command: &str) -> Result<String, TimeTrackerError> {
+        let not_running = |_| {
+            TimeTrackerError::ControlError(String::from(
+                "TimeTrack is not running, start it with `timetrack track`",
+            ))
+        };
+        let mut stream =
+            UnixStream::connect(self.config.control_socket_path()).map_err(not_running)?;
+        writeln!(stream, "{}", command).map_err(not_running)?;
+
+        let mut response = String::new();
+        for line in BufReader::new(stream).lines() {
+            let line = line.map_err(not_running)?;
+            response.push_str(&line);
+            response.push('\n');
+        }
+
+        Ok(response)
+    }
+
+    #[cfg(not(unix))]
+    fn send_control_command(&self, _command: &str) -> Result<String, TimeTrackerError> {
+        Err(TimeTrackerError::ControlError(String::from(
+            "controlling the tracker is not supported on your operating system",
+        )))
+    }
+}
+
+/// Parses a duration like `45s`, `30m` or `2h`
+fn parse_duration(duration: &str) -> Result<Duration, TimeTrackerError> {
+    let invalid = || TimeTrackerError::InvalidDurationError(duration.to_string());
+    let duration = duration.trim();
+    let split_at = duration
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+    let (value, unit) = duration.split_at(split_at);
+    let value = value.parse::<u64>().map_err(|_| invalid())?;
+
+    let seconds = match unit {
+        "s" => value,
+        "m" => value * 60,
+        "h" => value * 60 * 60,
+        _ => return Err(invalid()),
+    };
+
+    Ok(Duration::from_secs(seconds))
+}
+
+fn ago(seconds: u64) -> String {
+    match seconds {
+        0 => String::from("just now"),
+        seconds => format!("{} ago", to_hms(seconds)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(unix)]
+    use tempfile::tempdir;
+
+    #[test]
+    fn parse_durations() {
+        assert_eq!(Duration::from_secs(45), parse_duration("45s").unwrap());
+        assert_eq!(Duration::from_secs(30 * 60), parse_duration("30m").unwrap());
+        assert_eq!(
+            Duration::from_secs(2 * 60 * 60),
+            parse_duration("2h").unwrap()
+        );
+        assert!(parse_duration("30").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("30 minutes").is_err());
+    }
+
+    #[test]
+    fn timed_pause_resumes() {
+        let mut state = TrackerState::new(&[]);
+
+        state.pause(Some(Duration::from_secs(0)));
+        assert!(!state.is_paused());
+
+        state.pause(Some(Duration::from_secs(60)));
+        assert!(state.is_paused());
+
+        state.resume();
+        assert!(!state.is_paused());
+    }
+
+    #[test]
+    fn reload_requests() {
+        let mut state = TrackerState::new(&[]);
+        assert!(!state.take_reload_request());
+
+        state.request_reload();
+        assert!(state.take_reload_request());
+        assert!(!state.take_reload_request());
+    }
+
+    #[test]
+    fn handle_commands() {
+        let state = Mutex::new(TrackerState::new(&[PathBuf::from("/Users/josh/Projects")]));
+        for timestamp in &[1, now()] {
+            state.lock().unwrap().record(RawLog {
+                name: "testProj".to_string(),
+                timestamp: *timestamp,
+                language: None,
+                dir: None,
+                tag: None,
+            });
+        }
+
+        assert_eq!(
+            "Tracking paused for 30 minutes\n",
+            handle_command(&state, "pause 1800")
+        );
+        let status = handle_command(&state, "status");
+        assert!(status.contains("Tracking: paused for"));
+        assert_eq!("paused\n", handle_command(&state, "paused"));
+        assert!(status.contains("Events today: 2\n"));
+        assert!(status.contains("Current project: testProj"));
+
+        assert_eq!("Tracking resumed\n", handle_command(&state, "resume"));
+        let status = handle_command(&state, "status");
+        assert!(status.contains("Tracking: active"));
+        assert_eq!("active\n", handle_command(&state, "paused"));
+
+        handle_command(&state, "stop");
+        assert!(state.lock().unwrap().is_stop_requested());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn control_socket() {
+        let dir = tempdir().unwrap();
+        let config = Configuration::new_mock_config(
+            vec![dir.path().join("Projects")],
+            dir.path().join(".timetrack_raw"),
+            dir.path().join(".timetrack_processed"),
+        );
+        let state = Arc::new(Mutex::new(TrackerState::new(&config.track_paths)));
+        let tracker = TimeTracker::new(&config);
+
+        {
+            let _socket = ControlSocket::listen(&config, state.clone()).unwrap();
+            assert!(ControlSocket::listen(&config, state.clone()).is_err());
+
+            tracker.pause(Some("5m")).unwrap();
+            assert!(state.lock().unwrap().is_paused());
+            tracker.resume().unwrap();
+            assert!(!state.lock().unwrap().is_paused());
+        }
+
+        assert!(!config.control_socket_path().exists());
+        assert!(tracker.resume().is_err());
+    }
+}
@@ -5,6 +5,17 @@ use std::fmt;
 pub enum TimeTrackerError {
     InvalidLineError(String),
     InvalidTimestampError(String),
+    InvalidConfigError(String),
+    InvalidProjectError(String),
+    HeartbeatServerError(String),
+    ControlError(String),
+    InvalidDurationError(String),
+    AlreadyRunningError(u32),
+    LockError(String),
+    UnknownProfileError(String),
+    InvalidProfileError(String),
+    InvalidActivityError(String),
+    InvalidTimestampArgumentError(String),
 }
 
 impl Error for TimeTrackerError {
@@ -12,24 +23,93 @@ impl Error for TimeTrackerError {
         match *self {
             TimeTrackerError::InvalidLineError(..) => "could not parse line",
             TimeTrackerError::InvalidTimestampError(..) => "could not parse line",
+            TimeTrackerError::InvalidConfigError(..) => "could not read configuration",
+            TimeTrackerError::InvalidProjectError(..) => "invalid project name",
+            TimeTrackerError::HeartbeatServerError(..) => "could not start heartbeat API",
+            TimeTrackerError::ControlError(..) => "could not control the tracker",
+            TimeTrackerError::InvalidDurationError(..) => "invalid duration",
+            TimeTrackerError::AlreadyRunningError(..) => "already tracking",
+            TimeTrackerError::LockError(..) => "could not acquire lock file",
+            TimeTrackerError::UnknownProfileError(..) => "unknown profile",
+            TimeTrackerError::InvalidProfileError(..) => "invalid profile name",
+            TimeTrackerError::InvalidActivityError(..) => "invalid activity",
+            TimeTrackerError::InvalidTimestampArgumentError(..) => "invalid timestamp",
         }
     }
     fn cause(&self) -> Option<&dyn Error> {
         match *self {
             TimeTrackerError::InvalidLineError(..) => None,
             TimeTrackerError::InvalidTimestampError(..) => None,
+            TimeTrackerError::InvalidConfigError(..) => None,
+            TimeTrackerError::InvalidProjectError(..) => None,
+            TimeTrackerError::HeartbeatServerError(..) => None,
+            TimeTrackerError::ControlError(..) => None,
+            TimeTrackerError::InvalidDurationError(..) => None,
+            TimeTrackerError::AlreadyRunningError(..) => None,
+            TimeTrackerError::LockError(..) => None,
+            TimeTrackerError::UnknownProfileError(..) => None,
+            TimeTrackerError::InvalidProfileError(..) => None,
+            TimeTrackerError::InvalidActivityError(..) => None,
+            TimeTrackerError::InvalidTimestampArgumentError(..) => None,
         }
     }
 }
 
 impl fmt::Display for TimeTrackerError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}: ", self.description())?;
         match *self {
-            TimeTrackerError::InvalidLineError(ref v) => write!(f, "could not parse line: {}", v),
+            TimeTrackerError::InvalidLineError(ref v) => {
+                write!(f, "{}: could not parse line: {}", self.description(), v)
+            }
             TimeTrackerError::InvalidTimestampError(ref v) => {
-                write!(f, "could not parse line: {}", v)
+                write!(f, "{}: could not parse line: {}", self.description(), v)
+            }
+            TimeTrackerError::InvalidConfigError(ref v) => {
+                write!(f, "could not read configuration: {}", v)
+            }
+            TimeTrackerError::InvalidProjectError(ref v) => write!(
+                f,
+                "invalid project name: \
+                 project names can't be empty or contain slashes or newlines: {:?}",
+                v
+            ),
+            TimeTrackerError::HeartbeatServerError(ref v) => {
+                write!(f, "could not start heartbeat API: {}", v)
+            }
+            TimeTrackerError::ControlError(ref v) => {
+                write!(f, "could not control the tracker: {}", v)
             }
+            TimeTrackerError::InvalidDurationError(ref v) => write!(
+                f,
+                "invalid duration: \
+                 expected a number of seconds, minutes or hours like 30m: {:?}",
+                v
+            ),
+            TimeTrackerError::AlreadyRunningError(v) => write!(
+                f,
+                "already tracking: \
+                 TimeTrack is already tracking in process {}, stop it with `timetrack stop`",
+                v
+            ),
+            TimeTrackerError::LockError(ref v) => write!(f, "could not acquire lock file: {}", v),
+            TimeTrackerError::UnknownProfileError(ref v) => write!(
+                f,
+                "unknown profile: no profile named {:?} in the configuration file",
+                v
+            ),
+            TimeTrackerError::InvalidProfileError(ref v) => write!(
+                f,
+                "invalid profile name: \
+                 profile names can only contain letters, numbers, - and _: {:?}",
+                v
+            ),
+            TimeTrackerError::InvalidActivityError(ref v) => write!(f, "invalid activity: {}", v),
+            TimeTrackerError::InvalidTimestampArgumentError(ref v) => write!(
+                f,
+                "invalid timestamp: \
+                 expected a number of seconds since the Unix epoch like 1546300800: {:?}",
+                v
+            ),
         }
     }
 }
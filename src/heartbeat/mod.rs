@@ -0,0 +1,430 @@
+use crate::calc::raw_log::RawLog;
+use crate::config::Configuration;
+use crate::source::{ActivitySource, SourceControl};
+use crate::track::{activity_for_project, now, CONTROL_CHECK_INTERVAL};
+use crate::TimeTracker;
+use crate::TimeTrackerError;
+use log::{debug, log, warn};
+use serde_derive::Deserialize;
+use serde_json::json;
+use std::sync::mpsc::Sender;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// How far in the future a heartbeat may be, since the plugin's clock may be slightly ahead
+const MAX_CLOCK_SKEW_SECONDS: u64 = 5 * 60;
+
+/// A heartbeat in the protocol spoken by WakaTime editor plugins
+///
+/// Other fields, such as `is_write`, are ignored since reading a file is as much activity on its
+/// project as writing it.
+#[derive(Deserialize, Debug)]
+struct Heartbeat {
+    /// The file being worked on, or an app or domain depending on the type
+    entity: String,
+    #[serde(rename = "type")]
+    entity_type: Option<String>,
+    project: Option<String>,
+    language: Option<String>,
+    /// Seconds since the Unix epoch, with a fractional part
+    time: f64,
+}
+
+impl Heartbeat {
+    /// The time of the heartbeat in whole seconds, which can't be before the Unix epoch or in the
+    /// future
+    fn timestamp(&self) -> Result<u64, String> {
+        if !self.time.is_finite() || self.time < 0.0 {
+            return Err(format!("Invalid time {}", self.time));
+        }
+        if self.time > (now() + MAX_CLOCK_SKEW_SECONDS) as f64 {
+            return Err(format!("Time {} is in the future", self.time));
+        }
+        Ok(self.time as u64)
+    }
+}
+
+/// Accepts heartbeats from editor plugins over HTTP on localhost
+///
+/// Plugins send heartbeats to `<api_url>/users/current/heartbeats` (or `heartbeats.bulk` for
+/// several at once), so they can be pointed at `http://127.0.0.1:<port>/api/v1`.
+pub struct HeartbeatSource {
+    config: Configuration,
+    server: Server,
+}
+
+impl HeartbeatSource {
+    /// Starts listening on the port, which picks a free port if it is 0
+    pub fn bind(config: &Configuration, port: u16) -> Result<Self, TimeTrackerError> {
+        let server = Server::http(("127.0.0.1", port))
+            .map_err(|err| TimeTrackerError::HeartbeatServerError(err.to_string()))?;
+
+        Ok(HeartbeatSource {
+            config: config.clone(),
+            server,
+        })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.server.server_addr().port()
+    }
+}
+
+impl ActivitySource for HeartbeatSource {
+    fn name(&self) -> String {
+        format!("heartbeat API (port {})", self.port())
+    }
+
+    fn run(self: Box<Self>, sink: Sender<RawLog>, control: SourceControl) {
+        // projects are resolved with the reloaded configuration, though the port stays the same
+        let mut config = self.config.clone();
+        // wake up periodically to check if the tracker is shutting down or reloaded the configuration
+        while !control.is_shutting_down() {
+            if let Some(new_config) = control.take_reloaded_config() {
+                config = new_config;
+            }
+            match self.server.recv_timeout(CONTROL_CHECK_INTERVAL) {
+                Ok(Some(request)) => handle_request(&TimeTracker::new(&config), request, &sink),
+                Ok(None) => {}
+                Err(err) => {
+                    warn!("Failed to receive heartbeat ({})", err);
+                    return;
+                }
+            }
+        }
+    }
+}
+
+fn handle_request(tracker: &TimeTracker<'_>, mut request: Request, sink: &Sender<RawLog>) {
+    let mut body = String::new();
+    let (status, response) = if let Err(response) = check_headers(&request) {
+        response
+    } else if let Err(err) = request.as_reader().read_to_string(&mut body) {
+        (400, json!({ "error": err.to_string() }))
+    } else {
+        let path = request.url().split('?').next().unwrap_or("").to_string();
+        match (request.method(), path.as_str()) {
+            (Method::Post, path) if path.ends_with("/heartbeats") => {
+                match serde_json::from_str::<Heartbeat>(&body) {
+                    Ok(heartbeat) => match store_heartbeat(tracker, heartbeat, sink) {
+                        Ok(()) => (201, json!({ "data": {} })),
+                        Err(err) => (400, json!({ "error": err })),
+                    },
+                    Err(err) => (400, json!({ "error": err.to_string() })),
+                }
+            }
+            (Method::Post, path) if path.ends_with("/heartbeats.bulk") => {
+                match serde_json::from_str::<Vec<Heartbeat>>(&body) {
+                    Ok(heartbeats) => {
+                        let responses: Vec<_> = heartbeats
+                            .into_iter()
+                            .map(
+                                |heartbeat| match store_heartbeat(tracker, heartbeat, sink) {
+                                    Ok(()) => json!([{ "data": {} }, 201]),
+                                    Err(err) => json!([{ "error": err }, 400]),
+                                },
+                            )
+                            .collect();
+                        (201, json!({ "responses": responses }))
+                    }
+                    Err(err) => (400, json!({ "error": err.to_string() })),
+                }
+            }
+            _ => (404, json!({ "error": "Not found" })),
+        }
+    };
+
+    let content_type = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+        .expect("The content type header is valid");
+    let response = Response::from_string(response.to_string())
+        .with_status_code(status)
+        .with_header(content_type);
+    if let Err(err) = request.respond(response) {
+        debug!("Failed to respond to heartbeat ({})", err);
+    }
+}
+
+/// Only editor plugins may send heartbeats
+///
+/// Browsers send an `Origin` header with requests made by web pages, and can only send JSON from
+/// another origin with a preflight request, which is never answered.
+fn check_headers(request: &Request) -> Result<(), (u16, serde_json::Value)> {
+    let header = |name: &'static str| {
+        request
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv(name))
+            .map(|header| header.value.as_str())
+    };
+
+    if header("Origin").is_some() {
+        return Err((
+            403,
+            json!({ "error": "Requests from web pages are not accepted" }),
+        ));
+    }
+    match header("Content-Type") {
+        Some(content_type) if content_type.starts_with("application/json") => Ok(()),
+        _ => Err((415, json!({ "error": "Expected a JSON body" }))),
+    }
+}
+
+/// Heartbeats outside of any project are still accepted, otherwise plugins would keep resending them,
+/// while heartbeats with an invalid time are rejected
+fn store_heartbeat(
+    tracker: &TimeTracker<'_>,
+    heartbeat: Heartbeat,
+    sink: &Sender<RawLog>,
+) -> Result<(), String> {
+    let timestamp = heartbeat.timestamp()?;
+    if let Some(log) = activity_from_heartbeat(tracker, heartbeat, timestamp) {
+        sink.send(log)
+            .unwrap_or_else(|_| warn!("Failed to report heartbeat, the tracker has stopped"));
+    }
+    Ok(())
+}
+
+/// Resolves the heartbeat's file to its project the same way as file changes, falling back to
+/// the project named by the plugin
+fn activity_from_heartbeat(
+    tracker: &TimeTracker<'_>,
+    heartbeat: Heartbeat,
+    timestamp: u64,
+) -> Option<RawLog> {
+    let is_file = match &heartbeat.entity_type {
+        Some(entity_type) => entity_type == "file",
+        None => true,
+    };
+
+    let Heartbeat {
+        entity,
+        project,
+        language,
+        ..
+    } = heartbeat;
+
+    let log = if is_file {
+        tracker.activity_from_path(&entity, timestamp, language.clone())
+    } else {
+        None
+    };
+    let log = match log {
+        Some(log) => Ok(log),
+        None => activity_for_project(&project?, timestamp).map(|log| RawLog { language, ..log }),
+    };
+
+    // the plugin's language is written to the raw data as it is, so it can't break the line format
+    match log.and_then(|log| log.validate().map(|_| log)) {
+        Ok(log) => Some(log),
+        Err(err) => {
+            warn!("Skipping heartbeat ({})", err);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc::raw_log::raw_logs_from;
+    use std::io::Read;
+    use std::io::Write;
+    use std::net::TcpStream;
+    use std::path::PathBuf;
+    use std::sync::mpsc::channel;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn heartbeats_over_http() {
+        let config = get_mock_config();
+        let source = HeartbeatSource::bind(&config, 0).unwrap();
+        let port = source.port();
+        let (tx, rx) = channel();
+        thread::spawn(move || Box::new(source).run(tx, SourceControl::new()));
+
+        let response = post(
+            port,
+            "/api/v1/users/current/heartbeats",
+            r#"{"entity": "/Users/josh/Projects/testProj/src/main.rs", "type": "file", "is_write": true, "time": 1546300800.25}"#,
+        );
+        assert!(response.starts_with("HTTP/1.1 201"));
+        assert_eq!(
+            RawLog {
+                name: "testProj".to_string(),
+                timestamp: 1546300800,
+                language: Some("Rust".to_string()),
+                dir: Some("src".to_string()),
+                tag: None,
+            },
+            rx.recv_timeout(Duration::from_secs(5)).unwrap()
+        );
+
+        let response = post(
+            port,
+            "/api/v1/users/current/heartbeats.bulk",
+            r#"[{"entity": "https://docs.rs", "type": "domain", "project": "testOtherProj", "time": 1546300900}]"#,
+        );
+        assert!(response.starts_with("HTTP/1.1 201"));
+        assert_eq!(
+            "testOtherProj",
+            rx.recv_timeout(Duration::from_secs(5)).unwrap().name
+        );
+
+        let response = post(port, "/api/v1/users/current/heartbeats", "not json");
+        assert!(response.starts_with("HTTP/1.1 400"));
+    }
+
+    #[test]
+    fn heartbeats_with_invalid_times() {
+        let config = get_mock_config();
+        let source = HeartbeatSource::bind(&config, 0).unwrap();
+        let port = source.port();
+        let (tx, rx) = channel();
+        thread::spawn(move || Box::new(source).run(tx, SourceControl::new()));
+        let heartbeat = |time: &str| {
+            format!(
+                r#"{{"entity": "https://docs.rs", "type": "domain", "project": "testProj", "time": {}}}"#,
+                time
+            )
+        };
+
+        for time in &["-1", "1e400", &(now() + 60 * 60).to_string()] {
+            let response = post(port, "/api/v1/users/current/heartbeats", &heartbeat(time));
+            assert!(response.starts_with("HTTP/1.1 400"));
+        }
+
+        let response = post(
+            port,
+            "/api/v1/users/current/heartbeats.bulk",
+            &format!("[{}, {}]", heartbeat("-1"), heartbeat("1546300800")),
+        );
+        assert!(response.starts_with("HTTP/1.1 201"));
+        assert!(response.contains(r#"[{"error":"Invalid time -1"},400]"#));
+        assert_eq!(
+            1546300800,
+            rx.recv_timeout(Duration::from_secs(5)).unwrap().timestamp
+        );
+        assert!(rx.recv_timeout(Duration::from_millis(500)).is_err());
+    }
+
+    #[test]
+    fn heartbeat_timestamp() {
+        let heartbeat = |time: f64| Heartbeat {
+            entity: "https://docs.rs".to_string(),
+            entity_type: None,
+            project: None,
+            language: None,
+            time,
+        };
+
+        assert_eq!(Ok(1546300800), heartbeat(1546300800.75).timestamp());
+        // a clock which is slightly ahead is allowed
+        assert!(heartbeat((now() + 60) as f64).timestamp().is_ok());
+        assert!(heartbeat((now() + 60 * 60) as f64).timestamp().is_err());
+        assert!(heartbeat(-1.0).timestamp().is_err());
+        assert!(heartbeat(f64::NAN).timestamp().is_err());
+        assert!(heartbeat(f64::INFINITY).timestamp().is_err());
+    }
+
+    #[test]
+    fn heartbeats_which_would_corrupt_raw_data() {
+        let config = get_mock_config();
+        let source = HeartbeatSource::bind(&config, 0).unwrap();
+        let port = source.port();
+        let (tx, rx) = channel();
+        thread::spawn(move || Box::new(source).run(tx, SourceControl::new()));
+
+        let response = post(
+            port,
+            "/api/v1/users/current/heartbeats.bulk",
+            r#"[
+                {"entity": "/Users/josh/Projects/testProj/main.rs", "language": "Rust/x", "time": 1546300800},
+                {"entity": "https://docs.rs", "type": "domain", "project": "testProj", "language": "Rust\nevilProj/1", "time": 1546300800},
+                {"entity": "https://docs.rs", "type": "domain", "project": "testOtherProj", "language": "Rust", "time": 1546300900}
+            ]"#,
+        );
+        assert!(response.starts_with("HTTP/1.1 201"));
+
+        // only the valid heartbeat is stored, and it is read back as it was stored
+        let log = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(rx.recv_timeout(Duration::from_millis(500)).is_err());
+        assert_eq!(
+            vec![log],
+            raw_logs_from("testOtherProj/1546300900/Rust\n").unwrap()
+        );
+    }
+
+    #[test]
+    fn heartbeats_from_web_pages() {
+        let config = get_mock_config();
+        let source = HeartbeatSource::bind(&config, 0).unwrap();
+        let port = source.port();
+        let (tx, rx) = channel();
+        thread::spawn(move || Box::new(source).run(tx, SourceControl::new()));
+        let body = r#"{"entity": "https://docs.rs", "type": "domain", "project": "testProj", "time": 1546300800}"#;
+
+        let response = send(
+            port,
+            "/api/v1/users/current/heartbeats",
+            "Content-Type: application/json\r\nOrigin: https://example.com\r\n",
+            body,
+        );
+        assert!(response.starts_with("HTTP/1.1 403"));
+        let response = send(
+            port,
+            "/api/v1/users/current/heartbeats",
+            "Content-Type: text/plain\r\n",
+            body,
+        );
+        assert!(response.starts_with("HTTP/1.1 415"));
+
+        assert!(rx.recv_timeout(Duration::from_millis(500)).is_err());
+    }
+
+    #[test]
+    fn activity_from_heartbeat_outside_projects() {
+        let config = get_mock_config();
+        let tracker = TimeTracker::new(&config);
+        let heartbeat = Heartbeat {
+            entity: "/tmp/file1.rs".to_string(),
+            entity_type: None,
+            project: None,
+            language: None,
+            time: 1546300800.0,
+        };
+
+        assert_eq!(
+            None,
+            activity_from_heartbeat(&tracker, heartbeat, 1546300800)
+        );
+    }
+
+    fn post(port: u16, path: &str, body: &str) -> String {
+        send(port, path, "Content-Type: application/json\r\n", body)
+    }
+
+    fn send(port: u16, path: &str, headers: &str, body: &str) -> String {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        write!(
+            stream,
+            "POST {} HTTP/1.1\r\nHost: localhost\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            path,
+            headers,
+            body.len(),
+            body
+        )
+        .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    fn get_mock_config() -> Configuration {
+        Configuration::new_mock_config(
+            vec![PathBuf::from("/Users/josh/Projects")],
+            PathBuf::from("/Users/josh/.timetrack_raw"),
+            PathBuf::from("/Users/josh/.timetrack_processed"),
+        )
+    }
+}
@@ -1,14 +1,30 @@
 mod calc;
 mod clear;
 mod config;
+mod control;
 mod error;
+mod heartbeat;
+mod lock;
 mod schedule;
+mod shell;
+mod source;
 mod track;
 mod watcher;
 
 use crate::config::Configuration;
 
-pub use crate::config::get_config;
+pub use crate::calc::raw_log::RawLog;
+pub use crate::calc::Breakdown;
+pub use crate::config::check::check_config;
+pub use crate::config::{
+    get_config, get_config_with, get_user_config_path, ConfigFormat, ConfigOverrides,
+};
+pub use crate::heartbeat::HeartbeatSource;
+pub use crate::lock::ALREADY_RUNNING_EXIT_CODE;
+pub use crate::schedule::{ScheduleMethod, ScheduledCommand};
+pub use crate::shell::Shell;
+pub use crate::source::{ActivitySource, ReplaySource, SourceControl};
+pub use crate::track::FileSystemSource;
 
 pub use error::TimeTrackerError;
 
@@ -0,0 +1,190 @@
+use crate::config::Configuration;
+use crate::TimeTrackerError;
+use std::fs;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::fs::TryLockError;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process;
+use std::thread;
+use std::time::Duration;
+
+/// The exit code of a tracker which stops because another tracker is running
+///
+/// Service managers are told not to restart the tracker after this exit code, otherwise they
+/// would keep starting a second tracker.
+pub const ALREADY_RUNNING_EXIT_CODE: i32 = 3;
+
+/// Ensures only one tracker runs at a time, so activity isn't stored twice
+///
+/// The tracker holds an exclusive lock on the lock file, which the operating system releases when
+/// the tracker exits, even if it crashes. The file also holds the process id of the tracker, and
+/// is removed when the lock is dropped.
+pub(crate) struct InstanceLock {
+    path: PathBuf,
+    file: File,
+}
+
+impl InstanceLock {
+    pub fn acquire(config: &Configuration) -> Result<Self, TimeTrackerError> {
+        let path = config.lock_file_path();
+
+        // the tracker which held the lock removes the file as it exits, after which the lock is
+        // on a file which no longer exists, so the file is opened again
+        for _ in 0..3 {
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(&path)
+                .map_err(|err| TimeTrackerError::LockError(err.to_string()))?;
+            match file.try_lock() {
+                Ok(()) => {}
+                Err(TryLockError::WouldBlock) => {
+                    return Err(TimeTrackerError::AlreadyRunningError(read_locked_pid(
+                        &path,
+                    )));
+                }
+                Err(TryLockError::Error(err)) => {
+                    return Err(TimeTrackerError::LockError(err.to_string()));
+                }
+            }
+            if !is_same_file(&file, &path) {
+                continue;
+            }
+
+            // a file left behind by a tracker which didn't exit cleanly still holds its process id
+            file.set_len(0)
+                .and_then(|_| write!(&file, "{}", process::id()))
+                .map_err(|err| TimeTrackerError::LockError(err.to_string()))?;
+            return Ok(InstanceLock { path, file });
+        }
+
+        Err(TimeTrackerError::LockError(format!(
+            "another tracker acquired {:?} while starting",
+            path
+        )))
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        // removed while the lock is still held, so no other tracker has locked this file
+        if is_same_file(&self.file, &self.path) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// The process id of the tracker holding the lock, if one is running
+pub(crate) fn running_tracker(config: &Configuration) -> Option<u32> {
+    let path = config.lock_file_path();
+    let file = File::open(&path).ok()?;
+
+    match file.try_lock_shared() {
+        Err(TryLockError::WouldBlock) => Some(read_locked_pid(&path)),
+        // the lock is released when the file is closed
+        Ok(()) | Err(TryLockError::Error(_)) => None,
+    }
+}
+
+/// Reads the process id of the tracker holding the lock, which writes it right after locking
+fn read_locked_pid(path: &Path) -> u32 {
+    for _ in 0..10 {
+        if let Some(pid) = read_pid(path) {
+            return pid;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    0
+}
+
+fn read_pid(path: &Path) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Checks if the open file is still the file at the path, rather than one which was removed
+#[cfg(unix)]
+fn is_same_file(file: &File, path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (file.metadata(), fs::metadata(path)) {
+        (Ok(open), Ok(current)) => open.dev() == current.dev() && open.ino() == current.ino(),
+        _ => false,
+    }
+}
+
+/// Open files can't be removed on Windows, so the file at the path is always the open file
+#[cfg(not(unix))]
+fn is_same_file(_file: &File, path: &Path) -> bool {
+    path.exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn acquire_once() {
+        let dir = tempdir().unwrap();
+        let config = get_temp_config(dir.path());
+
+        {
+            let _lock = InstanceLock::acquire(&config).unwrap();
+            assert_eq!(Some(process::id()), running_tracker(&config));
+            match InstanceLock::acquire(&config) {
+                Err(TimeTrackerError::AlreadyRunningError(pid)) => assert_eq!(process::id(), pid),
+                _ => panic!("The lock should already be held"),
+            }
+        }
+
+        assert!(!config.lock_file_path().exists());
+        assert_eq!(None, running_tracker(&config));
+        assert!(InstanceLock::acquire(&config).is_ok());
+    }
+
+    #[test]
+    fn acquire_stale_lock() {
+        let dir = tempdir().unwrap();
+        let config = get_temp_config(dir.path());
+        // left behind by a tracker which crashed, or which crashed before writing its process id
+        for contents in &["2147483647", ""] {
+            fs::write(config.lock_file_path(), contents).unwrap();
+            assert_eq!(None, running_tracker(&config));
+
+            let _lock = InstanceLock::acquire(&config).unwrap();
+
+            assert_eq!(Some(process::id()), read_pid(&config.lock_file_path()));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn acquire_removed_lock() {
+        let dir = tempdir().unwrap();
+        let config = get_temp_config(dir.path());
+        let lock = InstanceLock::acquire(&config).unwrap();
+        // another tracker replaced the lock file while this one was exiting
+        fs::remove_file(config.lock_file_path()).unwrap();
+        let other_lock = InstanceLock::acquire(&config).unwrap();
+
+        drop(lock);
+
+        assert!(config.lock_file_path().exists());
+        drop(other_lock);
+        assert!(!config.lock_file_path().exists());
+    }
+
+    fn get_temp_config(dir: &Path) -> Configuration {
+        Configuration::new_mock_config(
+            vec![dir.join("Projects")],
+            dir.join(".timetrack_raw"),
+            dir.join(".timetrack_processed"),
+        )
+    }
+}
@@ -2,8 +2,20 @@ use clap::{crate_version, App};
 
 use clap::Arg;
 use clap::SubCommand;
-use timetrack::get_config;
+use std::path::PathBuf;
+use std::process;
+use timetrack::check_config;
+use timetrack::get_config_with;
+use timetrack::get_user_config_path;
+use timetrack::Breakdown;
+use timetrack::ConfigFormat;
+use timetrack::ConfigOverrides;
+use timetrack::ScheduleMethod;
+use timetrack::ScheduledCommand;
+use timetrack::Shell;
 use timetrack::TimeTracker;
+use timetrack::TimeTrackerError;
+use timetrack::ALREADY_RUNNING_EXIT_CODE;
 
 mod logger;
 use crate::logger::logger_init;
@@ -17,6 +29,24 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .multiple(true)
                 .help("Sets the level of verbosity (0-5, example: -vv for WARN)"),
         )
+        .arg(
+            Arg::with_name("config")
+                .long("config")
+                .takes_value(true)
+                .help("The configuration file to use, also set by TIMETRACK_CONFIG"),
+        )
+        .arg(
+            Arg::with_name("data-dir")
+                .long("data-dir")
+                .takes_value(true)
+                .help("The directory to keep tracking data in, also set by TIMETRACK_DATA_DIR"),
+        )
+        .arg(
+            Arg::with_name("profile")
+                .long("profile")
+                .takes_value(true)
+                .help("The named profile to use, from the profiles in the configuration file"),
+        )
         .subcommand(
             SubCommand::with_name("track")
                 .about("Starts the file system watcher for time tracking"),
@@ -25,33 +55,258 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             SubCommand::with_name("clear")
                 .about("Clear all TimeTrack history (Warning: this cannot be undone)"),
         )
-        .subcommand(SubCommand::with_name("config").about("Display the TimeTrack configuration"))
+        .subcommand(
+            SubCommand::with_name("report")
+                .about("Display how the time spent on each project is split")
+                .arg(
+                    Arg::with_name("by")
+                        .long("by")
+                        .takes_value(true)
+                        .possible_values(&["language", "dir", "tag"])
+                        .default_value("language")
+                        .help("The detail used to split the time spent on each project"),
+                )
+                .arg(
+                    Arg::with_name("project")
+                        .long("project")
+                        .takes_value(true)
+                        .help("Only display the given project"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("config")
+                .about("Display the TimeTrack configuration")
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["text", "json"])
+                        .default_value("text")
+                        .help("How to display the configuration and the self test results"),
+                )
+                .subcommand(
+                    SubCommand::with_name("add-path")
+                        .about("Start tracking a directory")
+                        .arg(Arg::with_name("dir").required(true)),
+                )
+                .subcommand(
+                    SubCommand::with_name("remove-path")
+                        .about("Stop tracking a directory")
+                        .arg(Arg::with_name("dir").required(true)),
+                )
+                .subcommand(
+                    SubCommand::with_name("set")
+                        .about("Change a setting, like `set dir_depth 2` or `set watcher poll`")
+                        .arg(Arg::with_name("key").required(true))
+                        .arg(Arg::with_name("value").required(true)),
+                )
+                .subcommand(
+                    SubCommand::with_name("edit")
+                        .about("Open the configuration file in $VISUAL or $EDITOR"),
+                )
+                .subcommand(
+                    SubCommand::with_name("check")
+                        .about("Check the configuration file, failing if it has any errors")
+                        .arg(
+                            Arg::with_name("strict")
+                                .long("strict")
+                                .help("Fail on warnings too, like unknown keys or missing track paths"),
+                        ),
+                ),
+        )
         .subcommand(
             SubCommand::with_name("schedule")
-                .about("Schedule TimeTrack to start tracking on login for the current user"),
+                .about("Schedule TimeTrack to start tracking on login for the current user")
+                .arg(
+                    Arg::with_name("method")
+                        .long("method")
+                        .takes_value(true)
+                        .possible_values(&["systemd", "xdg", "launchd", "cron"])
+                        .help("How to start TimeTrack, defaults to launchd on macOS and systemd on Linux"),
+                )
+                .arg(
+                    Arg::with_name("binary")
+                        .long("binary")
+                        .takes_value(true)
+                        .help("The TimeTrack binary to run, defaults to this binary"),
+                )
+                .arg(
+                    Arg::with_name("args")
+                        .multiple(true)
+                        .last(true)
+                        .help("Extra arguments for TimeTrack, given after --, like -- -vv"),
+                )
+                .arg(
+                    Arg::with_name("status")
+                        .long("status")
+                        .conflicts_with_all(&["method", "binary", "args"])
+                        .help("Display which methods TimeTrack is scheduled with"),
+                ),
         )
         .subcommand(
             SubCommand::with_name("unschedule")
                 .about("Disable automatic tracking on login for the current user"),
         )
+        .subcommand(SubCommand::with_name("status").about("Display the status of the running tracker"))
+        .subcommand(
+            SubCommand::with_name("pause")
+                .about("Stop recording activity in the running tracker until resumed")
+                .arg(
+                    Arg::with_name("for")
+                        .long("for")
+                        .takes_value(true)
+                        .help("Resume automatically after this long (example: 45s, 30m, 2h)"),
+                ),
+        )
+        .subcommand(SubCommand::with_name("resume").about("Resume recording activity in the running tracker"))
+        .subcommand(SubCommand::with_name("stop").about("Shut down the running tracker"))
+        .subcommand(
+            SubCommand::with_name("shell-hook")
+                .about("Print a snippet for your shell configuration which tracks the working directory")
+                .arg(
+                    Arg::with_name("shell")
+                        .required(true)
+                        .possible_values(&["bash", "zsh", "fish"]),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("ping")
+                .about("Record activity on the project containing the given path, or on the given project")
+                .arg(Arg::with_name("path").required_unless("project"))
+                .arg(
+                    Arg::with_name("project")
+                        .long("project")
+                        .takes_value(true)
+                        .conflicts_with("path")
+                        .help("Record activity on this project, rather than the project containing a path"),
+                )
+                .arg(
+                    Arg::with_name("timestamp")
+                        .long("timestamp")
+                        .takes_value(true)
+                        .help("When the activity happened, in seconds since the Unix epoch (defaults to now)"),
+                ),
+        )
         .get_matches();
 
     logger_init(matches.occurrences_of("v"));
 
-    let config = get_config();
+    let overrides = ConfigOverrides {
+        config_path: matches.value_of_os("config").map(PathBuf::from),
+        data_dir: matches.value_of_os("data-dir").map(PathBuf::from),
+        profile: matches.value_of("profile").map(String::from),
+    }
+    .resolve();
+
+    // checked before loading the configuration, which fails if it is invalid
+    if let Some(("check", Some(matches))) = matches
+        .subcommand_matches("config")
+        .map(|matches| matches.subcommand())
+    {
+        check_config(
+            &get_user_config_path(&overrides),
+            matches.is_present("strict"),
+        )?;
+        return Ok(());
+    }
+
+    let config = get_config_with(&overrides)?;
     let time_tracker = TimeTracker::new(&config);
 
     if matches.subcommand_matches("clear").is_some() {
         // TODO don't unwrap inside the library calls, handle errors here and exit with appropriate error message and exit code
         time_tracker.clear();
     } else if matches.subcommand_matches("track").is_some() {
-        time_tracker.track();
-    } else if matches.subcommand_matches("config").is_some() {
-        time_tracker.print_config();
-    } else if matches.subcommand_matches("schedule").is_some() {
-        time_tracker.schedule();
+        match time_tracker.track() {
+            Err(err @ TimeTrackerError::AlreadyRunningError(_)) => {
+                eprintln!("Error: {}", err);
+                process::exit(ALREADY_RUNNING_EXIT_CODE);
+            }
+            result => result?,
+        }
+    } else if let Some(matches) = matches.subcommand_matches("report") {
+        let breakdown = match matches.value_of("by") {
+            Some("language") => Breakdown::Language,
+            Some("dir") => Breakdown::Dir,
+            Some("tag") => Breakdown::Tag,
+            _ => unreachable!("clap only accepts the possible values"),
+        };
+        time_tracker.report(&breakdown, matches.value_of("project"))?;
+    } else if let Some(matches) = matches.subcommand_matches("config") {
+        match matches.subcommand() {
+            ("add-path", Some(matches)) => {
+                time_tracker.add_track_path(matches.value_of_os("dir").expect("dir is required"))?
+            }
+            ("remove-path", Some(matches)) => time_tracker
+                .remove_track_path(matches.value_of_os("dir").expect("dir is required"))?,
+            ("set", Some(matches)) => time_tracker.set_config(
+                matches.value_of("key").expect("key is required"),
+                matches.value_of("value").expect("value is required"),
+            )?,
+            ("edit", Some(_)) => time_tracker.edit_config()?,
+            _ => {
+                let format = match matches.value_of("format") {
+                    Some("json") => ConfigFormat::Json,
+                    _ => ConfigFormat::Text,
+                };
+                time_tracker.print_config(&format)
+            }
+        }
+    } else if let Some(matches) = matches.subcommand_matches("schedule") {
+        if matches.is_present("status") {
+            time_tracker.print_schedule_status();
+        } else {
+            let method = matches.value_of("method").map(|method| match method {
+                "systemd" => ScheduleMethod::Systemd,
+                "xdg" => ScheduleMethod::Xdg,
+                "launchd" => ScheduleMethod::Launchd,
+                "cron" => ScheduleMethod::Cron,
+                _ => unreachable!("clap only accepts the possible values"),
+            });
+            // the schedule uses the same configuration and data as this command
+            let mut args = overrides.to_args();
+            if let Some(extra_args) = matches.values_of("args") {
+                args.extend(extra_args.map(String::from));
+            }
+            let command =
+                ScheduledCommand::new(matches.value_of("binary").map(PathBuf::from), args);
+            time_tracker.schedule(method, &command);
+        }
     } else if matches.subcommand_matches("unschedule").is_some() {
         time_tracker.unschedule();
+    } else if matches.subcommand_matches("status").is_some() {
+        time_tracker.status();
+    } else if let Some(matches) = matches.subcommand_matches("pause") {
+        time_tracker.pause(matches.value_of("for"))?;
+    } else if matches.subcommand_matches("resume").is_some() {
+        time_tracker.resume()?;
+    } else if matches.subcommand_matches("stop").is_some() {
+        time_tracker.stop()?;
+    } else if let Some(matches) = matches.subcommand_matches("shell-hook") {
+        let shell = match matches.value_of("shell") {
+            Some("bash") => Shell::Bash,
+            Some("zsh") => Shell::Zsh,
+            Some("fish") => Shell::Fish,
+            _ => unreachable!("clap only accepts the possible values"),
+        };
+        // the hook pings with the same configuration and data as this command
+        time_tracker.print_shell_hook(&shell, &overrides.to_args());
+    } else if let Some(matches) = matches.subcommand_matches("ping") {
+        let timestamp = match matches.value_of("timestamp") {
+            Some(timestamp) => Some(timestamp.parse::<u64>().map_err(|_| {
+                TimeTrackerError::InvalidTimestampArgumentError(timestamp.to_string())
+            })?),
+            None => None,
+        };
+        match matches.value_of("project") {
+            Some(project) => time_tracker.ping_project(project, timestamp)?,
+            None => time_tracker.ping(
+                matches
+                    .value_of("path")
+                    .expect("path is required without a project"),
+                timestamp,
+            ),
+        }
     } else {
         time_tracker.calc()?;
     }
@@ -0,0 +1,124 @@
+use super::ScheduledCommand;
+use std::io;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Marks the crontab line TimeTrack manages, so it can be replaced or removed later
+const MARKER: &str = "# timetrack";
+
+/// The entry starts the tracker when the computer starts, rather than on login
+fn get_crontab_entry(command: &ScheduledCommand) -> String {
+    let arguments: Vec<_> = command
+        .program_arguments()
+        .iter()
+        .map(|argument| quote_argument(argument))
+        .collect();
+    format!("@reboot {} >/dev/null 2>&1 {}", arguments.join(" "), MARKER)
+}
+
+/// Quotes the argument for the shell which runs crontab entries
+fn quote_argument(argument: &str) -> String {
+    // `%` starts the command's standard input in a crontab, unless escaped
+    let argument = argument.replace('\'', r#"'\''"#).replace('%', r"\%");
+    format!("'{}'", argument)
+}
+
+fn is_entry(line: &str) -> bool {
+    line.trim_end().ends_with(MARKER)
+}
+
+/// Replaces any previous TimeTrack entry, keeping the rest of the crontab
+fn add_entry(crontab: &str, command: &ScheduledCommand) -> String {
+    let mut crontab = remove_entry(crontab);
+    crontab.push_str(&get_crontab_entry(command));
+    crontab.push('\n');
+    crontab
+}
+
+fn remove_entry(crontab: &str) -> String {
+    crontab
+        .lines()
+        .filter(|line| !is_entry(line))
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+/// `crontab -l` fails when the user has no crontab yet, which is the same as an empty one
+fn read_crontab() -> String {
+    Command::new("crontab")
+        .arg("-l")
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+        .unwrap_or_default()
+}
+
+fn write_crontab(crontab: &str) -> io::Result<()> {
+    let mut child = Command::new("crontab")
+        .arg("-")
+        .stdin(Stdio::piped())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .expect("The crontab input is piped")
+        .write_all(crontab.as_bytes())?;
+
+    let status = child.wait()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("crontab exited with {}", status)))
+    }
+}
+
+pub fn install(command: &ScheduledCommand) -> io::Result<()> {
+    write_crontab(&add_entry(&read_crontab(), command))
+}
+
+pub fn uninstall() -> io::Result<()> {
+    write_crontab(&remove_entry(&read_crontab()))
+}
+
+pub fn is_installed() -> bool {
+    read_crontab().lines().any(is_entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn crontab_entry() {
+        let command = ScheduledCommand {
+            binary_path: PathBuf::from("/home/josh/100% josh's/timetrack"),
+            args: vec![String::from("-vv")],
+        };
+
+        assert_eq!(
+            r"@reboot '/home/josh/100\% josh'\''s/timetrack' '-vv' 'track' >/dev/null 2>&1 # timetrack",
+            get_crontab_entry(&command)
+        );
+    }
+
+    #[test]
+    fn add_and_remove_entry() {
+        let crontab = "MAILTO=josh\n0 * * * * backup\n";
+        let command = ScheduledCommand {
+            binary_path: PathBuf::from("/usr/local/bin/timetrack"),
+            args: vec![],
+        };
+
+        let scheduled = add_entry(crontab, &command);
+
+        assert_eq!(
+            format!("{}{}\n", crontab, get_crontab_entry(&command)),
+            scheduled
+        );
+        assert_eq!(scheduled, add_entry(&scheduled, &command));
+        assert_eq!(crontab, remove_entry(&scheduled));
+    }
+}
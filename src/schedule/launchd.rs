@@ -0,0 +1,82 @@
+use super::ScheduledCommand;
+use directories::UserDirs;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// The launch agent is started by launchd on login
+pub fn get_plist_file_contents(command: &ScheduledCommand) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+    <dict>
+        <key>Label</key>
+        <string>rust.cargo.timetrack</string>
+        <key>ProgramArguments</key>
+        <array>
+{}        </array>
+        <key>RunAtLoad</key>
+        <true/>
+    </dict>
+</plist>
+"#,
+        command
+            .program_arguments()
+            .iter()
+            .map(|argument| format!("            <string>{}</string>\n", escape_xml(argument)))
+            .collect::<String>()
+    )
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn get_plist_file_path() -> PathBuf {
+    UserDirs::new()
+        .expect("Unable to find home directory")
+        .home_dir()
+        .join("Library/LaunchAgents/rust.cargo.timetrack.plist")
+}
+
+pub fn install(command: &ScheduledCommand) -> io::Result<()> {
+    let plist_file_path = get_plist_file_path();
+    if let Some(dir) = plist_file_path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(plist_file_path, get_plist_file_contents(command))
+}
+
+pub fn uninstall() -> io::Result<()> {
+    fs::remove_file(get_plist_file_path())
+}
+
+pub fn is_installed() -> bool {
+    get_plist_file_path().exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plist_file_contents() {
+        let command = ScheduledCommand {
+            binary_path: PathBuf::from("/Users/josh/R&D/timetrack"),
+            args: vec![String::from("-vv")],
+        };
+        let contents = get_plist_file_contents(&command);
+
+        assert!(contents.contains(
+            "<array>
+            <string>/Users/josh/R&amp;D/timetrack</string>
+            <string>-vv</string>
+            <string>track</string>
+        </array>"
+        ));
+        assert!(contents.contains("<key>RunAtLoad</key>"));
+    }
+}
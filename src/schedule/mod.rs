@@ -1,73 +1,357 @@
 use crate::TimeTracker;
-use directories::UserDirs;
+use log::{info, log, warn};
+use serde_derive::{Deserialize, Serialize};
+use std::env;
+use std::fmt;
+use std::fmt::{Display, Formatter};
 use std::fs;
-use std::fs::OpenOptions;
-use std::io::Write;
+use std::io;
+use std::path::Path;
 use std::path::PathBuf;
 
-#[allow(dead_code)]
-fn not_supported() {
-    println!("Schedule configuration is not supported on your operating system");
+mod cron;
+mod launchd;
+mod systemd;
+mod xdg;
+
+/// The mechanisms which `schedule` can use to start tracking automatically
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScheduleMethod {
+    /// A systemd user service, started on login and restarted if it fails
+    Systemd,
+    /// An XDG autostart desktop entry, started on login by most Linux desktop environments
+    Xdg,
+    /// A macOS launch agent, started on login
+    Launchd,
+    /// A crontab entry, started when the computer starts
+    Cron,
 }
 
-fn get_plist_file_contents() -> String {
-    format!(
-        r#"<?xml version="1.0" encoding="UTF-8"?>
-<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
-<plist version="1.0">
-    <dict>
-        <key>Label</key>
-        <string>rust.cargo.timetrack</string>
-        <key>ProgramArguments</key>
-        <array>
-            <string>{}/.cargo/bin/timetrack</string>
-            <string>track</string>
-        </array>
-        <key>RunAtLoad</key>
-        <true/>
-    </dict>
-</plist>
-"#,
-        UserDirs::new().unwrap().home_dir().to_string_lossy()
-    ).to_string()
+/// The command a schedule runs, `<binary> <args...> track`
+///
+/// It is recorded when TimeTrack is scheduled, so the schedule can be updated if the binary moves.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ScheduledCommand {
+    pub binary_path: PathBuf,
+    /// Extra arguments for TimeTrack, like `-vv` for more logging
+    pub args: Vec<String>,
+}
+
+impl ScheduledCommand {
+    /// Runs the given binary, or the binary which is running now, wherever it was installed
+    pub fn new(binary_path: Option<PathBuf>, args: Vec<String>) -> Self {
+        let current_dir = env::current_dir().expect("Unable to find the current directory");
+        let binary_path = match binary_path {
+            // relative paths would be resolved from wherever the schedule runs
+            Some(binary_path) => current_dir.join(binary_path),
+            None => env::current_exe().expect("Unable to find the TimeTrack binary"),
+        };
+
+        ScheduledCommand { binary_path, args }
+    }
+
+    /// The binary followed by its arguments
+    fn program_arguments(&self) -> Vec<String> {
+        let mut arguments = vec![self.binary_path.to_string_lossy().to_string()];
+        arguments.extend(self.args.iter().cloned());
+        arguments.push(String::from("track"));
+        arguments
+    }
+
+    /// The same command with the current binary, if the scheduled binary no longer exists
+    fn moved_to(&self, current_exe: &Path) -> Option<ScheduledCommand> {
+        if self.binary_path.exists() || self.binary_path == current_exe {
+            return None;
+        }
+
+        Some(ScheduledCommand {
+            binary_path: current_exe.to_path_buf(),
+            args: self.args.clone(),
+        })
+    }
+}
+
+impl Display for ScheduledCommand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.program_arguments().join(" "))
+    }
+}
+
+fn read_scheduled_command(path: &Path) -> Option<ScheduledCommand> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+fn write_scheduled_command(path: &Path, command: &ScheduledCommand) -> io::Result<()> {
+    let json = serde_json::to_string(command).expect("The scheduled command is serializable");
+    fs::write(path, json)
+}
+
+impl ScheduleMethod {
+    const ALL: [ScheduleMethod; 4] = [
+        ScheduleMethod::Systemd,
+        ScheduleMethod::Xdg,
+        ScheduleMethod::Launchd,
+        ScheduleMethod::Cron,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ScheduleMethod::Systemd => "systemd",
+            ScheduleMethod::Xdg => "xdg",
+            ScheduleMethod::Launchd => "launchd",
+            ScheduleMethod::Cron => "cron",
+        }
+    }
+
+    fn is_supported(self) -> bool {
+        match self {
+            ScheduleMethod::Systemd | ScheduleMethod::Xdg => cfg!(target_os = "linux"),
+            ScheduleMethod::Launchd => cfg!(target_os = "macos"),
+            ScheduleMethod::Cron => cfg!(unix),
+        }
+    }
+
+    /// The method used when none is given
+    fn platform_default() -> Option<ScheduleMethod> {
+        if cfg!(target_os = "macos") {
+            Some(ScheduleMethod::Launchd)
+        } else if cfg!(target_os = "linux") {
+            Some(ScheduleMethod::Systemd)
+        } else {
+            None
+        }
+    }
+
+    fn supported() -> Vec<ScheduleMethod> {
+        ScheduleMethod::ALL
+            .iter()
+            .cloned()
+            .filter(|method| method.is_supported())
+            .collect()
+    }
+
+    /// Installs the method, or updates it to run the command
+    fn install(self, command: &ScheduledCommand) -> io::Result<()> {
+        match self {
+            ScheduleMethod::Systemd => systemd::install(command),
+            ScheduleMethod::Xdg => xdg::install(command),
+            ScheduleMethod::Launchd => launchd::install(command),
+            ScheduleMethod::Cron => cron::install(command),
+        }
+    }
+
+    /// Starts the installed method, returning a message describing when tracking will start
+    fn start(self) -> &'static str {
+        match self {
+            ScheduleMethod::Systemd => {
+                if systemd::enable() {
+                    "TimeTrack scheduled and started. It will start tracking on every login."
+                } else {
+                    "TimeTrack service installed, but it could not be enabled. \
+                     Enable it with `systemctl --user enable --now timetrack.service`."
+                }
+            }
+            ScheduleMethod::Xdg | ScheduleMethod::Launchd => {
+                "TimeTrack scheduled. Logout/login to start tracking."
+            }
+            ScheduleMethod::Cron => {
+                "TimeTrack scheduled. It will start tracking when the computer starts."
+            }
+        }
+    }
+
+    fn uninstall(self) -> io::Result<()> {
+        match self {
+            ScheduleMethod::Systemd => systemd::uninstall(),
+            ScheduleMethod::Xdg => xdg::uninstall(),
+            ScheduleMethod::Launchd => launchd::uninstall(),
+            ScheduleMethod::Cron => cron::uninstall(),
+        }
+    }
+
+    fn is_installed(self) -> bool {
+        match self {
+            ScheduleMethod::Systemd => systemd::is_installed(),
+            ScheduleMethod::Xdg => xdg::is_installed(),
+            ScheduleMethod::Launchd => launchd::is_installed(),
+            ScheduleMethod::Cron => cron::is_installed(),
+        }
+    }
 }
 
-fn get_plist_file_path() -> PathBuf {
-    UserDirs::new()
-        .unwrap()
-        .home_dir()
-        .join("Library/LaunchAgents/rust.cargo.timetrack.plist")
+fn not_supported() {
+    println!("Schedule configuration is not supported on your operating system");
 }
 
 impl<'a> TimeTracker<'a> {
-    #[cfg(target_os = "macos")]
-    pub fn schedule(&self) {
-        let mut f = OpenOptions::new()
-            .write(true)
-            .truncate(true)
-            .create(true)
-            .open(get_plist_file_path())
-            .expect("Unable to open or create plist file");
+    /// Schedules tracking with the given method, or the usual method for the operating system
+    pub fn schedule(&self, method: Option<ScheduleMethod>, command: &ScheduledCommand) {
+        let method = match method.or_else(ScheduleMethod::platform_default) {
+            Some(method) if method.is_supported() => method,
+            _ => return not_supported(),
+        };
 
-        write!(&mut f, "{}", get_plist_file_contents()).expect("Failed to schedule TimeTrack");
-        println!("TimeTrack scheduled. Logout/login to start tracking.");
+        if let Err(err) = method.install(command) {
+            return println!("Failed to schedule TimeTrack ({})", err);
+        }
+        if let Err(err) = write_scheduled_command(&self.config.schedule_file_path(), command) {
+            warn!("Failed to record the scheduled command ({})", err);
+        }
+        println!("{}", method.start());
     }
 
-    #[cfg(not(target_os = "macos"))]
-    pub fn schedule(&self) {
-        not_supported();
+    /// Updates the installed schedules to run this binary, if the scheduled binary has moved
+    ///
+    /// The schedules aren't started again, since this runs when the tracker starts.
+    pub(crate) fn refresh_schedule(&self) {
+        let schedule_file_path = self.config.schedule_file_path();
+        let moved = match (
+            read_scheduled_command(&schedule_file_path),
+            env::current_exe(),
+        ) {
+            (Some(command), Ok(current_exe)) => command.moved_to(&current_exe),
+            _ => None,
+        };
+        let command = match moved {
+            Some(command) => command,
+            None => return,
+        };
+
+        for method in ScheduleMethod::supported() {
+            if !method.is_installed() {
+                continue;
+            }
+            match method.install(&command) {
+                Ok(_) => info!("Updated the {} schedule to run {}", method.name(), command),
+                Err(err) => warn!("Failed to update the {} schedule ({})", method.name(), err),
+            }
+        }
+        if let Err(err) = write_scheduled_command(&schedule_file_path, &command) {
+            warn!("Failed to record the scheduled command ({})", err);
+        }
     }
 
-    #[cfg(target_os = "macos")]
+    /// Removes TimeTrack from every method it is scheduled with
     pub fn unschedule(&self) {
-        match fs::remove_file(get_plist_file_path()) {
-            Ok(_) => println!("TimeTrack schedule removed."),
-            Err(_) => println!("Failed to remove TimeTrack schedule."),
+        let supported = ScheduleMethod::supported();
+        if supported.is_empty() {
+            return not_supported();
         }
+
+        let installed: Vec<_> = supported
+            .into_iter()
+            .filter(|method| method.is_installed())
+            .collect();
+        if installed.is_empty() {
+            println!("TimeTrack is not scheduled.");
+        }
+
+        for method in installed {
+            match method.uninstall() {
+                Ok(_) => println!("TimeTrack schedule removed ({}).", method.name()),
+                Err(_) => println!("Failed to remove TimeTrack schedule ({}).", method.name()),
+            }
+        }
+        let _ = fs::remove_file(self.config.schedule_file_path());
     }
 
-    #[cfg(not(target_os = "macos"))]
-    pub fn unschedule(&self) {
-        not_supported();
+    /// Prints which methods TimeTrack is currently scheduled with
+    pub fn print_schedule_status(&self) {
+        let supported = ScheduleMethod::supported();
+        if supported.is_empty() {
+            return not_supported();
+        }
+
+        for method in supported {
+            let status = if method.is_installed() {
+                "installed"
+            } else {
+                "not installed"
+            };
+            println!("{}: {}", method.name(), status);
+        }
+        if let Some(command) = read_scheduled_command(&self.config.schedule_file_path()) {
+            println!("Runs: {}", command);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn platform_default_is_supported() {
+        if let Some(method) = ScheduleMethod::platform_default() {
+            assert!(method.is_supported());
+            assert!(ScheduleMethod::supported().contains(&method));
+        }
+    }
+
+    #[test]
+    fn relative_binary_path() {
+        let command = ScheduledCommand::new(Some(PathBuf::from("bin/timetrack")), vec![]);
+
+        assert_eq!(
+            env::current_dir().unwrap().join("bin/timetrack"),
+            command.binary_path
+        );
+    }
+
+    #[test]
+    fn program_arguments() {
+        let command = ScheduledCommand {
+            binary_path: PathBuf::from("/usr/local/bin/timetrack"),
+            args: vec![String::from("-vv")],
+        };
+
+        assert_eq!(
+            vec!["/usr/local/bin/timetrack", "-vv", "track"],
+            command.program_arguments()
+        );
+        assert_eq!("/usr/local/bin/timetrack -vv track", command.to_string());
+    }
+
+    #[test]
+    fn moved_binary() {
+        let dir = tempdir().unwrap();
+        let current_exe = dir.path().join("timetrack");
+        fs::write(&current_exe, "").unwrap();
+        let command = ScheduledCommand {
+            binary_path: dir.path().join("old/timetrack"),
+            args: vec![String::from("-vv")],
+        };
+
+        assert_eq!(
+            Some(ScheduledCommand {
+                binary_path: current_exe.clone(),
+                args: vec![String::from("-vv")],
+            }),
+            command.moved_to(&current_exe)
+        );
+
+        let command = ScheduledCommand {
+            binary_path: current_exe.clone(),
+            args: vec![],
+        };
+
+        assert_eq!(None, command.moved_to(&dir.path().join("other/timetrack")));
+    }
+
+    #[test]
+    fn read_and_write_scheduled_command() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(".timetrack_schedule");
+        let command = ScheduledCommand {
+            binary_path: PathBuf::from("/usr/local/bin/timetrack"),
+            args: vec![String::from("-vv")],
+        };
+
+        assert_eq!(None, read_scheduled_command(&path));
+
+        write_scheduled_command(&path, &command).unwrap();
+
+        assert_eq!(Some(command), read_scheduled_command(&path));
     }
 }
@@ -0,0 +1,150 @@
+use super::ScheduledCommand;
+use crate::lock::ALREADY_RUNNING_EXIT_CODE;
+use directories::BaseDirs;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+
+const SERVICE_NAME: &str = "timetrack.service";
+
+pub fn get_unit_file_contents(command: &ScheduledCommand) -> String {
+    format!(
+        r#"[Unit]
+Description=TimeTrack automatic time tracking
+
+[Service]
+ExecStart={}
+Restart=on-failure
+RestartSec=10
+RestartPreventExitStatus={}
+
+[Install]
+WantedBy=default.target
+"#,
+        command
+            .program_arguments()
+            .iter()
+            .map(|argument| format!("\"{}\"", escape_exec_argument(argument)))
+            .collect::<Vec<_>>()
+            .join(" "),
+        ALREADY_RUNNING_EXIT_CODE
+    )
+}
+
+/// Quotes and backslashes are escaped inside the quoted argument, and `%` would start a specifier
+fn escape_exec_argument(argument: &str) -> String {
+    argument
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('%', "%%")
+}
+
+/// The directory systemd reads the current user's units from, which respects `XDG_CONFIG_HOME`
+fn get_unit_dir() -> PathBuf {
+    BaseDirs::new()
+        .expect("Unable to find home directory")
+        .config_dir()
+        .join("systemd/user")
+}
+
+pub fn write_unit_file(unit_dir: &Path, command: &ScheduledCommand) -> io::Result<PathBuf> {
+    fs::create_dir_all(unit_dir)?;
+    let unit_file_path = unit_dir.join(SERVICE_NAME);
+    fs::write(&unit_file_path, get_unit_file_contents(command))?;
+    Ok(unit_file_path)
+}
+
+pub fn remove_unit_file(unit_dir: &Path) -> io::Result<()> {
+    fs::remove_file(unit_dir.join(SERVICE_NAME))
+}
+
+/// Installs or updates the service, without starting it
+pub fn install(command: &ScheduledCommand) -> io::Result<()> {
+    write_unit_file(&get_unit_dir(), command)?;
+    // systemd may not be running, in which case the unit is loaded on the next login
+    systemctl(&["daemon-reload"]);
+    Ok(())
+}
+
+/// Enables the service and starts it right away rather than on the next login, which fails if
+/// the user has no systemd session
+pub fn enable() -> bool {
+    systemctl(&["enable", "--now", SERVICE_NAME])
+}
+
+pub fn uninstall() -> io::Result<()> {
+    // the service may already be disabled, or systemd may not be running
+    systemctl(&["disable", "--now", SERVICE_NAME]);
+    remove_unit_file(&get_unit_dir())?;
+    systemctl(&["daemon-reload"]);
+    Ok(())
+}
+
+pub fn is_installed() -> bool {
+    get_unit_dir().join(SERVICE_NAME).exists()
+}
+
+fn systemctl(args: &[&str]) -> bool {
+    Command::new("systemctl")
+        .arg("--user")
+        .args(args)
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use tempfile::tempdir;
+
+    #[test]
+    fn unit_file_contents() {
+        let contents = get_unit_file_contents(&get_mock_command(vec![]));
+
+        assert!(contents.contains("ExecStart=\"/home/josh/.local/bin/timetrack\" \"track\"\n"));
+        assert!(contents.contains("Restart=on-failure\n"));
+        assert!(contents.contains("RestartPreventExitStatus=3\n"));
+        assert!(contents.contains("WantedBy=default.target\n"));
+    }
+
+    #[test]
+    fn unit_file_escapes_arguments() {
+        let command = ScheduledCommand {
+            binary_path: PathBuf::from("/home/josh/100% \"tools\"/timetrack"),
+            args: vec![String::from("-vv")],
+        };
+
+        assert!(get_unit_file_contents(&command)
+            .contains(r#"ExecStart="/home/josh/100%% \"tools\"/timetrack" "-vv" "track""#));
+    }
+
+    #[test]
+    fn write_and_remove_unit_file() {
+        let dir = tempdir().unwrap();
+        let unit_dir = dir.path().join("systemd/user");
+        let command = get_mock_command(vec![String::from("-vvv")]);
+
+        let unit_file_path = write_unit_file(&unit_dir, &command).unwrap();
+
+        assert_eq!(unit_dir.join("timetrack.service"), unit_file_path);
+        assert_eq!(
+            get_unit_file_contents(&command),
+            fs::read_to_string(&unit_file_path).unwrap()
+        );
+
+        remove_unit_file(&unit_dir).unwrap();
+
+        assert!(!unit_file_path.exists());
+    }
+
+    fn get_mock_command(args: Vec<String>) -> ScheduledCommand {
+        ScheduledCommand {
+            binary_path: PathBuf::from("/home/josh/.local/bin/timetrack"),
+            args,
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use super::ScheduledCommand;
+use directories::BaseDirs;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+const DESKTOP_FILE_NAME: &str = "timetrack.desktop";
+
+/// Desktop environments following the XDG autostart specification run the entry on login
+pub fn get_desktop_file_contents(command: &ScheduledCommand) -> String {
+    format!(
+        r#"[Desktop Entry]
+Type=Application
+Name=TimeTrack
+Comment=Automatic time tracking
+Exec={}
+Terminal=false
+X-GNOME-Autostart-enabled=true
+"#,
+        command
+            .program_arguments()
+            .iter()
+            .map(|argument| format!("\"{}\"", escape_exec_argument(argument)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    )
+}
+
+/// Escapes a quoted argument in `Exec`
+///
+/// Desktop entry values are unescaped before the arguments are unquoted, so a backslash inside a
+/// quoted argument has to be written as four backslashes.
+fn escape_exec_argument(argument: &str) -> String {
+    let mut escaped = String::new();
+    for c in argument.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\\\\\"),
+            '"' | '`' | '$' => {
+                escaped.push_str("\\\\");
+                escaped.push(c);
+            }
+            '%' => escaped.push_str("%%"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The autostart directory, which respects `XDG_CONFIG_HOME`
+fn get_autostart_dir() -> PathBuf {
+    BaseDirs::new()
+        .expect("Unable to find home directory")
+        .config_dir()
+        .join("autostart")
+}
+
+pub fn write_desktop_file(autostart_dir: &Path, command: &ScheduledCommand) -> io::Result<PathBuf> {
+    fs::create_dir_all(autostart_dir)?;
+    let desktop_file_path = autostart_dir.join(DESKTOP_FILE_NAME);
+    fs::write(&desktop_file_path, get_desktop_file_contents(command))?;
+    Ok(desktop_file_path)
+}
+
+pub fn install(command: &ScheduledCommand) -> io::Result<()> {
+    write_desktop_file(&get_autostart_dir(), command).map(|_| ())
+}
+
+pub fn uninstall() -> io::Result<()> {
+    fs::remove_file(get_autostart_dir().join(DESKTOP_FILE_NAME))
+}
+
+pub fn is_installed() -> bool {
+    get_autostart_dir().join(DESKTOP_FILE_NAME).exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn desktop_file_contents() {
+        let command = ScheduledCommand {
+            binary_path: PathBuf::from("/home/josh/.local/bin/timetrack"),
+            args: vec![String::from("-vv")],
+        };
+        let contents = get_desktop_file_contents(&command);
+
+        assert!(contents.starts_with("[Desktop Entry]\n"));
+        assert!(contents.contains("Exec=\"/home/josh/.local/bin/timetrack\" \"-vv\" \"track\"\n"));
+    }
+
+    #[test]
+    fn desktop_file_escapes_arguments() {
+        let command = ScheduledCommand {
+            binary_path: PathBuf::from(r#"/home/josh/$HOME\100% "tools"/timetrack"#),
+            args: vec![],
+        };
+
+        assert!(get_desktop_file_contents(&command)
+            .contains(r#"Exec="/home/josh/\\$HOME\\\\100%% \\"tools\\"/timetrack" "track""#));
+    }
+
+    #[test]
+    fn write_desktop_file_into_autostart_dir() {
+        let dir = tempdir().unwrap();
+        let autostart_dir = dir.path().join("autostart");
+        let command = ScheduledCommand {
+            binary_path: PathBuf::from("/usr/local/bin/timetrack"),
+            args: vec![],
+        };
+
+        let desktop_file_path = write_desktop_file(&autostart_dir, &command).unwrap();
+
+        assert_eq!(autostart_dir.join("timetrack.desktop"), desktop_file_path);
+        assert_eq!(
+            get_desktop_file_contents(&command),
+            fs::read_to_string(&desktop_file_path).unwrap()
+        );
+    }
+}
@@ -0,0 +1,95 @@
+use crate::TimeTracker;
+
+/// The shells which `shell-hook` can print a prompt hook for
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl<'a> TimeTracker<'a> {
+    /// Prints a snippet which pings TimeTrack with the working directory every time the prompt is shown
+    ///
+    /// The arguments, like `--profile work`, are passed to every ping so it uses the same
+    /// configuration and data as this command.
+    pub fn print_shell_hook(&self, shell: &Shell, args: &[String]) {
+        print!("{}", get_shell_hook(shell, args));
+    }
+}
+
+fn get_shell_hook(shell: &Shell, args: &[String]) -> String {
+    let command: String = args
+        .iter()
+        .map(|arg| format!(" {}", quote_argument(shell, arg)))
+        .collect();
+    get_shell_hook_template(shell).replace("timetrack ping", &format!("timetrack{} ping", command))
+}
+
+/// Quotes the argument in single quotes, which every supported shell reads literally
+fn quote_argument(shell: &Shell, argument: &str) -> String {
+    let argument = match shell {
+        Shell::Bash | Shell::Zsh => argument.replace('\'', r#"'\''"#),
+        // fish allows escaping quotes and backslashes within single quotes
+        Shell::Fish => argument.replace('\\', r"\\").replace('\'', r"\'"),
+    };
+    format!("'{}'", argument)
+}
+
+// the ping runs in the background, so a slow disk never delays the prompt
+fn get_shell_hook_template(shell: &Shell) -> &'static str {
+    match shell {
+        Shell::Bash => {
+            r#"_timetrack_hook() {
+    (timetrack ping "$PWD" >/dev/null 2>&1 &)
+}
+if [[ ";${PROMPT_COMMAND:-};" != *";_timetrack_hook;"* ]]; then
+    PROMPT_COMMAND="_timetrack_hook${PROMPT_COMMAND:+;$PROMPT_COMMAND}"
+fi
+"#
+        }
+        Shell::Zsh => {
+            r#"_timetrack_hook() {
+    (timetrack ping "$PWD" >/dev/null 2>&1 &)
+}
+autoload -Uz add-zsh-hook
+add-zsh-hook precmd _timetrack_hook
+"#
+        }
+        Shell::Fish => {
+            r#"function __timetrack_hook --on-event fish_prompt
+    command timetrack ping "$PWD" >/dev/null 2>&1 &
+    disown 2>/dev/null
+end
+"#
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shell_hooks_ping_working_directory() {
+        for shell in &[Shell::Bash, Shell::Zsh, Shell::Fish] {
+            assert!(get_shell_hook(shell, &[]).contains(r#"timetrack ping "$PWD""#));
+        }
+    }
+
+    #[test]
+    fn shell_hooks_keep_overrides() {
+        let args = vec![
+            String::from("--config"),
+            String::from("/Users/josh/josh's config"),
+            String::from("--profile"),
+            String::from("work"),
+        ];
+
+        assert!(get_shell_hook(&Shell::Bash, &args).contains(
+            r#"timetrack '--config' '/Users/josh/josh'\''s config' '--profile' 'work' ping "$PWD""#
+        ));
+        assert!(get_shell_hook(&Shell::Fish, &args).contains(
+            r#"timetrack '--config' '/Users/josh/josh\'s config' '--profile' 'work' ping "$PWD""#
+        ));
+    }
+}
@@ -0,0 +1,159 @@
+use crate::calc::raw_log::RawLog;
+use crate::config::Configuration;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+
+/// Something which reports activity on projects, such as file system changes or editor heartbeats
+///
+/// Each source runs on its own thread, and the activity from every source is stored together.
+pub trait ActivitySource: Send {
+    /// A short description of the source, used in logs
+    fn name(&self) -> String;
+
+    /// Sends activity to the sink until the source has nothing more to report
+    ///
+    /// This blocks, so sources which never finish (like the file system watcher) run until the
+    /// tracker shuts them down through the control.
+    fn run(self: Box<Self>, sink: Sender<RawLog>, control: SourceControl);
+}
+
+/// Lets the tracker shut down a running source or give it a reloaded configuration, and lets the
+/// source ask the tracker to reload the configuration
+#[derive(Clone, Default)]
+pub struct SourceControl {
+    shutdown: Arc<AtomicBool>,
+    reload_requested: Arc<AtomicBool>,
+    reloaded_config: Arc<Mutex<Option<Configuration>>>,
+}
+
+impl SourceControl {
+    pub fn new() -> Self {
+        SourceControl::default()
+    }
+
+    /// Sources should return soon after this, sending any activity they have buffered first
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutdown.load(Ordering::SeqCst)
+    }
+
+    /// Returns the configuration the tracker reloaded since the last check, which the source should
+    /// use from now on
+    pub fn take_reloaded_config(&self) -> Option<Configuration> {
+        self.reloaded_config
+            .lock()
+            .expect("Failed to lock reloaded configuration")
+            .take()
+    }
+
+    /// Asks the tracker to reload the configuration, for example when the source saw the user
+    /// configuration file change
+    pub fn request_reload(&self) {
+        self.reload_requested.store(true, Ordering::SeqCst);
+    }
+
+    pub fn shut_down(&self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+    }
+
+    /// Checks if the source asked for a reload since the last check
+    pub fn take_reload_request(&self) -> bool {
+        self.reload_requested.swap(false, Ordering::SeqCst)
+    }
+
+    pub fn reload(&self, config: Configuration) {
+        *self
+            .reloaded_config
+            .lock()
+            .expect("Failed to lock reloaded configuration") = Some(config);
+    }
+}
+
+/// Reports activity which was already recorded, for example from another machine's raw data
+pub struct ReplaySource {
+    logs: Vec<RawLog>,
+}
+
+impl ReplaySource {
+    pub fn new(logs: Vec<RawLog>) -> Self {
+        ReplaySource { logs }
+    }
+}
+
+impl ActivitySource for ReplaySource {
+    fn name(&self) -> String {
+        format!("replay of {} logs", self.logs.len())
+    }
+
+    fn run(self: Box<Self>, sink: Sender<RawLog>, control: SourceControl) {
+        for log in self.logs {
+            if control.is_shutting_down() || sink.send(log).is_err() {
+                // the tracker stopped, so there is nowhere to store the remaining logs
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::sync::mpsc::channel;
+
+    #[test]
+    fn replay_source() {
+        let (tx, rx) = channel();
+        let source = ReplaySource::new(vec![
+            get_mock_log("testProj", 1),
+            get_mock_log("testOtherProj", 2),
+        ]);
+
+        Box::new(source).run(tx, SourceControl::new());
+
+        assert_eq!(
+            vec![
+                get_mock_log("testProj", 1),
+                get_mock_log("testOtherProj", 2)
+            ],
+            rx.iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn source_control() {
+        let control = SourceControl::new();
+        let source_control = control.clone();
+
+        source_control.request_reload();
+        assert!(control.take_reload_request());
+        assert!(!control.take_reload_request());
+
+        let config = Configuration::new_mock_config(
+            vec![PathBuf::from("/Users/josh/Projects")],
+            PathBuf::from("/Users/josh/.timetrack_raw"),
+            PathBuf::from("/Users/josh/.timetrack_processed"),
+        );
+        control.reload(config.clone());
+        assert_eq!(
+            Some(config.track_paths),
+            source_control
+                .take_reloaded_config()
+                .map(|config| config.track_paths)
+        );
+        assert!(source_control.take_reloaded_config().is_none());
+
+        control.shut_down();
+        assert!(source_control.is_shutting_down());
+    }
+
+    fn get_mock_log(name: &str, timestamp: u64) -> RawLog {
+        RawLog {
+            name: name.to_string(),
+            timestamp,
+            language: None,
+            dir: None,
+            tag: None,
+        }
+    }
+}
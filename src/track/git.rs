@@ -1,35 +1,96 @@
 use log::{debug, log};
-use std::ffi::OsStr;
-use std::path::Path;
-use std::process::Command;
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Component, Path};
+use std::process::{Command, Stdio};
+use std::thread;
 
-pub fn contains_file_which_would_not_be_ignored<I, S, P>(dir: P, paths: I) -> bool
+/// Returns the paths which git would not ignore
+///
+/// Every path is kept outside of a git repository, or if git can't be run. Files within `.git`
+/// are never kept, since they are git's own files rather than changes to the project, and git
+/// doesn't consider them ignored.
+pub fn files_which_would_not_be_ignored<P>(dir: P, paths: &[String]) -> Vec<String>
 where
-    I: IntoIterator<Item = S>,
-    S: AsRef<OsStr>,
     P: AsRef<Path>,
 {
-    match Command::new("git")
+    let paths: Vec<String> = paths
+        .iter()
+        .filter(|path| {
+            !Path::new(path)
+                .components()
+                .any(|component| component == Component::Normal(".git".as_ref()))
+        })
+        .cloned()
+        .collect();
+
+    match check_ignore(dir.as_ref(), &paths) {
+        Some(not_ignored) => paths
+            .into_iter()
+            .filter(|path| not_ignored.contains(path))
+            .collect(),
+        None => paths,
+    }
+}
+
+/// Runs `git check-ignore`, returning the paths which no ignore rule matched, or `None` if the
+/// paths aren't in a git repository
+///
+/// Paths are passed separated by NUL characters, so they are never quoted or split.
+fn check_ignore(dir: &Path, paths: &[String]) -> Option<HashSet<String>> {
+    let mut child = match Command::new("git")
         .current_dir(dir)
-        .args(&["check-ignore", "-v", "--no-index", "-n"])
-        .args(paths)
-        .output()
+        .args(["check-ignore", "-v", "--no-index", "-n", "-z", "--stdin"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
     {
-        Ok(output) => {
-            String::from_utf8_lossy(&output.stdout)
-                .lines()
-                .any(|line| line.contains("::"))
-                || String::from_utf8_lossy(&output.stderr)
-                    .lines()
-                    .any(|line| line.starts_with("fatal: Not a git repository"))
-        }
+        Ok(child) => child,
         Err(err) => {
             // this could be running git outside of a git repo (file change detected outside of a git repo)
             // or if the user doesn't have git installed
             debug!("{}", err);
-            true
+            return None;
         }
+    };
+
+    // written from another thread, so a long list of paths can't fill the output pipe while git
+    // waits for more input
+    let mut stdin = child.stdin.take().expect("The git input is piped");
+    let input: Vec<u8> = paths
+        .iter()
+        .flat_map(|path| path.bytes().chain(Some(0)))
+        .collect();
+    let writer = thread::spawn(move || stdin.write_all(&input));
+
+    let output = match child.wait_with_output() {
+        Ok(output) => output,
+        Err(err) => {
+            debug!("{}", err);
+            return None;
+        }
+    };
+    let _ = writer.join();
+
+    if String::from_utf8_lossy(&output.stderr).lines().any(|line| {
+        line.to_lowercase()
+            .starts_with("fatal: not a git repository")
+    }) {
+        return None;
     }
+
+    // each path is reported as `<source> NUL <line> NUL <pattern> NUL <path> NUL`, where the
+    // source is empty for paths which aren't ignored
+    let output = String::from_utf8_lossy(&output.stdout);
+    let fields: Vec<&str> = output.split('\0').collect();
+    Some(
+        fields
+            .chunks_exact(4)
+            .filter(|record| record[0].is_empty())
+            .map(|record| record[3].to_string())
+            .collect(),
+    )
 }
 
 #[cfg(test)]
@@ -38,20 +99,33 @@ mod tests {
     use std::env;
 
     #[test]
-    fn contains() {
-        let file_paths = vec!["target", "not-ignored"];
-        assert!(contains_file_which_would_not_be_ignored(
-            env::current_dir().unwrap(),
-            file_paths
-        ));
+    fn not_ignored() {
+        let file_paths = vec![
+            String::from("target"),
+            String::from("not-ignored"),
+            String::from(".git/hooks/pre-commit.sample"),
+        ];
+        assert_eq!(
+            vec![String::from("not-ignored")],
+            files_which_would_not_be_ignored(env::current_dir().unwrap(), &file_paths)
+        );
+    }
+
+    #[test]
+    fn all_ignored() {
+        let file_paths = vec![String::from("target")];
+        assert!(
+            files_which_would_not_be_ignored(env::current_dir().unwrap(), &file_paths).is_empty()
+        );
     }
 
     #[test]
-    fn does_not_contains() {
-        let file_paths = vec!["target"];
-        assert!(!contains_file_which_would_not_be_ignored(
-            env::current_dir().unwrap(),
-            file_paths
-        ));
+    fn outside_git_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_paths = vec![String::from("target"), String::from(".git/config")];
+        assert_eq!(
+            vec![String::from("target")],
+            files_which_would_not_be_ignored(dir.path(), &file_paths)
+        );
     }
 }
@@ -0,0 +1,109 @@
+use std::ffi::OsStr;
+use std::path::Component;
+use std::path::Path;
+
+/// File names which configure continuous integration, regardless of their extension
+const CI_FILE_NAMES: &[&str] = &[
+    ".travis.yml",
+    ".gitlab-ci.yml",
+    "appveyor.yml",
+    ".appveyor.yml",
+    "azure-pipelines.yml",
+    "Jenkinsfile",
+];
+
+/// Directories whose contents configure continuous integration
+const CI_DIRECTORIES: &[&str] = &[".github", ".circleci"];
+
+/// Classifies a changed file by language (or broader file type, such as docs or CI config)
+///
+/// Files with an unrecognized extension are classified by their extension, and files
+/// without any extension are not classified.
+pub fn classify<P>(path: P) -> Option<String>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+
+    let is_ci_file = path
+        .file_name()
+        .and_then(OsStr::to_str)
+        .is_some_and(|file_name| CI_FILE_NAMES.contains(&file_name));
+    let is_in_ci_directory = path.components().any(|component| match component {
+        Component::Normal(name) => name
+            .to_str()
+            .is_some_and(|name| CI_DIRECTORIES.contains(&name)),
+        _ => false,
+    });
+    if is_ci_file || is_in_ci_directory {
+        return Some(String::from("CI"));
+    }
+
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    let language = match extension.as_str() {
+        "rs" => "Rust",
+        "md" | "markdown" | "rst" | "adoc" | "txt" => "Docs",
+        "toml" => "TOML",
+        "yml" | "yaml" => "YAML",
+        "json" => "JSON",
+        "py" => "Python",
+        "js" | "jsx" | "mjs" => "JavaScript",
+        "ts" | "tsx" => "TypeScript",
+        "go" => "Go",
+        "c" | "h" => "C",
+        "cc" | "cpp" | "cxx" | "hpp" => "C++",
+        "java" => "Java",
+        "kt" | "kts" => "Kotlin",
+        "rb" => "Ruby",
+        "swift" => "Swift",
+        "sh" | "bash" | "zsh" | "fish" => "Shell",
+        "html" | "htm" => "HTML",
+        "css" | "scss" | "sass" => "CSS",
+        "sql" => "SQL",
+        _ => return Some(extension),
+    };
+
+    Some(String::from(language))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_known_extension() {
+        assert_eq!(
+            Some(String::from("Rust")),
+            classify("/Users/josh/Projects/timetrack/src/main.rs")
+        );
+        assert_eq!(
+            Some(String::from("Docs")),
+            classify("/Users/josh/Projects/timetrack/README.md")
+        );
+    }
+
+    #[test]
+    fn classify_ci_config() {
+        assert_eq!(
+            Some(String::from("CI")),
+            classify("/Users/josh/Projects/timetrack/.travis.yml")
+        );
+        assert_eq!(
+            Some(String::from("CI")),
+            classify("/Users/josh/Projects/timetrack/.github/workflows/ci.yml")
+        );
+    }
+
+    #[test]
+    fn classify_unknown_extension() {
+        assert_eq!(
+            Some(String::from("lock")),
+            classify("/Users/josh/Projects/timetrack/Cargo.LOCK")
+        );
+    }
+
+    #[test]
+    fn classify_no_extension() {
+        assert_eq!(None, classify("/Users/josh/Projects/timetrack/LICENSE"));
+    }
+}
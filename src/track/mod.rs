@@ -1,95 +1,434 @@
+use crate::calc::raw_log::RawLog;
+use crate::config::hours::OffHours;
+use crate::config::path::{normalize_path, strip_path_prefix};
+use crate::config::Configuration;
+use crate::control::TrackerState;
+#[cfg(unix)]
+use crate::control::{handle_signals, ControlSocket};
+use crate::heartbeat::HeartbeatSource;
+use crate::lock::InstanceLock;
+use crate::source::{ActivitySource, SourceControl};
 use crate::watcher;
+use crate::watcher::WatcherSet;
 use crate::TimeTracker;
-use log::{debug, error, log, trace};
+use crate::TimeTrackerError;
+use log::{debug, error, info, log, trace, warn};
 use notify::DebouncedEvent;
+use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::fs::OpenOptions;
 use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
 use std::sync::mpsc::channel;
-use std::sync::mpsc::TryRecvError;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 use std::time::Instant;
 use std::time::SystemTime;
 
 mod git;
+mod language;
 
-impl<'a> TimeTracker<'a> {
-    pub fn track(&self) {
-        let (tx, rx) = channel();
+/// How often track paths which could not be watched are retried
+const RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How often the tracker and its sources check for requests to stop or reload
+pub(crate) const CONTROL_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The tag of activity outside of the working hours, with `off_hours = "tag"`
+const OFF_HOURS_TAG: &str = "off-hours";
 
-        let mut watchers = vec![]; // need to keep ownership of watchers so they aren't dropped at end of for-loop
+/// How long sources have to store their buffered activity when the tracker stops
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The number of events and paths handled in one batch, logged to help tune the batch window
+#[derive(Default, PartialEq, Debug)]
+struct BatchStats {
+    /// Events received from the watchers
+    received: usize,
+    /// Paths skipped because they were ignored, outside the projects, or git ignored
+    filtered: usize,
+    /// Records sent to be written to the raw data file
+    stored: usize,
+}
+
+/// Reports changes to files within the track paths as activity on the project containing them
+pub struct FileSystemSource {
+    config: Configuration,
+}
 
+impl FileSystemSource {
+    pub fn new(config: &Configuration) -> Self {
+        FileSystemSource {
+            config: config.clone(),
+        }
+    }
+}
+
+impl ActivitySource for FileSystemSource {
+    fn name(&self) -> String {
+        String::from("file system")
+    }
+
+    fn run(self: Box<Self>, sink: Sender<RawLog>, control: SourceControl) {
+        let (tx, rx) = channel();
+
+        // need to keep ownership of watchers so they aren't dropped
+        let mut watchers = WatcherSet::new(tx.clone(), self.config.watcher_options());
         for track_path in &self.config.track_paths {
-            // errors are silent here, but reported by timetrack config
-            if let Ok(watcher) = watcher::get_watcher(track_path, tx.clone()) {
-                watchers.push(watcher);
+            watchers.watch(track_path);
+        }
+        let _config_watcher = self.config.user_config_dir().and_then(|dir| {
+            match watcher::get_directory_watcher(dir, tx) {
+                Ok(watcher) => Some(watcher),
+                Err(err) => {
+                    warn!("Failed to watch the configuration for changes ({})", err);
+                    None
+                }
+            }
+        });
+
+        TimeTracker::new(&self.config).track_events(&rx, &mut watchers, &sink, &control);
+        // the watchers are dropped when this returns, which stops watching
+        info!("Stopped watching for file changes");
+    }
+}
+
+impl<'a> TimeTracker<'a> {
+    /// Tracks the selected profile, and every named profile when the default profile is selected
+    ///
+    /// Each profile is tracked on its own thread into its own data files, and stopping the default
+    /// profile stops them all.
+    pub fn track(&self) -> Result<(), TimeTrackerError> {
+        let profiles = if self.config.profile.is_none() {
+            self.config
+                .profile_names
+                .iter()
+                .map(|name| self.config.for_profile(Some(name)))
+                .collect::<Result<Vec<_>, _>>()?
+        } else {
+            vec![]
+        };
+
+        // taken first, so a tracker which is already running isn't disturbed by a second one
+        let _lock = InstanceLock::acquire(self.config)?;
+
+        // a tracker started from a new location keeps its schedule pointing at the installed binary
+        self.refresh_schedule();
+
+        let tracker_control = SourceControl::new();
+        let profile_trackers: Vec<_> = profiles
+            .into_iter()
+            .map(|config| {
+                let tracker_control = tracker_control.clone();
+                let name = config.profile.clone().unwrap_or_default();
+                thread::Builder::new()
+                    .name(format!("{} profile", name))
+                    .spawn(move || {
+                        let tracker = TimeTracker::new(&config);
+                        if let Err(err) =
+                            tracker.track_sources_with(tracker.sources(), &tracker_control)
+                        {
+                            error!("Failed to track the {} profile ({})", name, err);
+                        }
+                    })
+                    .expect("Failed to start profile tracker")
+            })
+            .collect();
+
+        let result = self.track_sources_locked(self.sources(), &tracker_control);
+        tracker_control.shut_down();
+        for profile_tracker in profile_trackers {
+            let _ = profile_tracker.join();
+        }
+
+        result
+    }
+
+    /// The sources of activity for the profile, file changes and optionally the heartbeat API
+    fn sources(&self) -> Vec<Box<dyn ActivitySource>> {
+        let mut sources: Vec<Box<dyn ActivitySource>> =
+            vec![Box::new(FileSystemSource::new(self.config))];
+        if let Some(port) = self.config.heartbeat_port {
+            match HeartbeatSource::bind(self.config, port) {
+                Ok(source) => sources.push(Box::new(source)),
+                Err(err) => error!(
+                    "Failed to start the heartbeat API on port {} ({})",
+                    port, err
+                ),
             }
         }
 
-        let mut first_record_time;
-        let write_delay = Duration::from_secs(2);
+        sources
+    }
+
+    /// Runs each source on its own thread and stores their activity, until every source has finished
+    /// or the tracker is stopped with `timetrack stop`
+    pub fn track_sources(
+        &self,
+        sources: Vec<Box<dyn ActivitySource>>,
+    ) -> Result<(), TimeTrackerError> {
+        self.track_sources_with(sources, &SourceControl::new())
+    }
+
+    /// Tracks the sources like `track_sources`, also stopping when the tracker control is shut down
+    fn track_sources_with(
+        &self,
+        sources: Vec<Box<dyn ActivitySource>>,
+        tracker_control: &SourceControl,
+    ) -> Result<(), TimeTrackerError> {
+        // held until tracking stops, so a second tracker can't store the same activity again
+        let _lock = InstanceLock::acquire(self.config)?;
+        self.track_sources_locked(sources, tracker_control)
+    }
+
+    /// Tracks the sources like `track_sources_with`, once the profile's lock is held
+    fn track_sources_locked(
+        &self,
+        sources: Vec<Box<dyn ActivitySource>>,
+        tracker_control: &SourceControl,
+    ) -> Result<(), TimeTrackerError> {
+        let (tx, rx) = channel();
+
+        let mut controls = vec![];
+        for source in sources {
+            let sink = tx.clone();
+            let control = SourceControl::new();
+            controls.push(control.clone());
+            let name = source.name();
+            thread::Builder::new()
+                .name(name.clone())
+                .spawn(move || {
+                    info!("Tracking activity from the {} source", name);
+                    source.run(sink, control);
+                    info!("The {} source finished", name);
+                })
+                .expect("Failed to start activity source");
+        }
+        // the channel disconnects once every source has dropped its sink
+        drop(tx);
 
+        let state = Arc::new(Mutex::new(TrackerState::new(&self.config.track_paths)));
+        #[cfg(unix)]
+        let _control_socket = ControlSocket::listen(self.config, state.clone())
+            .map_err(|err| warn!("Failed to start the control socket ({})", err))
+            .ok();
+        #[cfg(unix)]
+        let signals = handle_signals(state.clone());
+
+        // the configuration is reloaded while tracking, and the sources are given each reloaded configuration
+        let mut config = self.config.clone();
+
+        // wake up periodically to check if the tracker was asked to stop or reload
+        let mut sources_finished = false;
         loop {
-            let mut events = vec![];
-
-            // block waiting for the first event
-            match rx.recv() {
-                Ok(event) => {
-                    first_record_time = Instant::now();
-                    events.push(event);
-
-                    // wait a small amount of additional time to see if other events come in
-                    loop {
-                        match rx.try_recv() {
-                            Ok(event) => events.push(event),
-                            Err(TryRecvError::Empty) => thread::sleep(Duration::from_millis(100)),
-                            Err(e) => println!("watch error: {:?}", e),
-                        };
-
-                        if first_record_time.elapsed() >= write_delay {
-                            break;
-                        }
-                    }
+            match rx.recv_timeout(CONTROL_CHECK_INTERVAL) {
+                Ok(log) => TimeTracker::new(&config).store_activity(&state, log),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => {
+                    sources_finished = true;
+                    break;
                 }
-                Err(e) => println!("watch error: {:?}", e),
             }
 
-            events
-                .iter()
-                .filter_map(get_path_from_event)
-                .filter_map(|path| match self.extract_project_name(path) {
-                    None => None,
-                    Some(project) => {
-                        trace!("File change detected on {:?}", path);
-                        Some((project, path))
+            // every request is taken, so one which arrives with another isn't handled again later
+            let mut reload_requested = state
+                .lock()
+                .expect("Failed to lock tracker state")
+                .take_reload_request();
+            for control in &controls {
+                reload_requested |= control.take_reload_request();
+            }
+            if reload_requested {
+                reload_config(&mut config, &state, &controls);
+            }
+
+            if tracker_control.is_shutting_down()
+                || state
+                    .lock()
+                    .expect("Failed to lock tracker state")
+                    .is_stop_requested()
+            {
+                break;
+            }
+        }
+
+        if !sources_finished {
+            // sources send the activity they have buffered (like a batch of file changes) before finishing
+            info!("Stopping tracking");
+            for control in &controls {
+                control.shut_down();
+            }
+            let shutdown_start = Instant::now();
+            while let Some(remaining) = SHUTDOWN_TIMEOUT.checked_sub(shutdown_start.elapsed()) {
+                match rx.recv_timeout(remaining) {
+                    Ok(log) => TimeTracker::new(&config).store_activity(&state, log),
+                    Err(RecvTimeoutError::Timeout) => {
+                        warn!("Activity sources did not finish in time");
+                        break;
                     }
-                })
-                .fold(HashMap::new(), |mut acc, (project, path)| {
-                    acc.entry(project)
-                        .or_insert_with(Vec::new)
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+        }
+
+        #[cfg(unix)]
+        {
+            if let Some(signals) = signals {
+                signals.close();
+            }
+        }
+
+        Ok(())
+    }
+
+    fn store_activity(&self, state: &Mutex<TrackerState>, log: RawLog) {
+        let mut state = state.lock().expect("Failed to lock tracker state");
+        if state.is_paused() {
+            debug!("Tracking is paused, skipping {}", log);
+        } else if let Some(log) = self.during_working_hours(log) {
+            self.store(&log);
+            state.record(log);
+        }
+    }
+
+    /// Drops or tags activity outside of the working hours, such as a nightly build touching a project
+    fn during_working_hours(&self, mut log: RawLog) -> Option<RawLog> {
+        let working_hours = &self.config.working_hours;
+        if working_hours.contains(log.timestamp) {
+            return Some(log);
+        }
+
+        match working_hours.off_hours {
+            OffHours::Drop => {
+                debug!("Outside of working hours, skipping {}", log);
+                None
+            }
+            OffHours::Tag => {
+                log.tag = Some(String::from(OFF_HOURS_TAG));
+                Some(log)
+            }
+        }
+    }
+
+    /// Stores the events received from the watchers in batches, until shut down or every sender is dropped
+    fn track_events(
+        &self,
+        rx: &Receiver<DebouncedEvent>,
+        watchers: &mut WatcherSet,
+        sink: &Sender<RawLog>,
+        control: &SourceControl,
+    ) {
+        // the tracker reloads the configuration when the user configuration file changes
+        let mut config = self.config.clone();
+        let mut last_retry_time = Instant::now();
+
+        loop {
+            if last_retry_time.elapsed() >= RETRY_INTERVAL {
+                watchers.retry_failed();
+                last_retry_time = Instant::now();
+            }
+
+            // the last batch is stored before shutting down
+            if control.is_shutting_down() {
+                return;
+            }
+
+            if let Some(new_config) = control.take_reloaded_config() {
+                watchers.update(&new_config.track_paths, new_config.watcher_options());
+                config = new_config;
+            }
+
+            // wake up periodically to retry failed watches, and to check for requests from the tracker
+            let events = match receive_batch(
+                rx,
+                watchers,
+                CONTROL_CHECK_INTERVAL,
+                config.batch_window,
+                control,
+            ) {
+                Ok(events) => events,
+                Err(RecvTimeoutError::Timeout) => vec![],
+                Err(RecvTimeoutError::Disconnected) => {
+                    info!("All watchers stopped, no longer tracking");
+                    return;
+                }
+            };
+
+            if !events.is_empty() {
+                // events received before a configuration change are stored using the previous configuration
+                let stats = TimeTracker::new(&config).store_events(&events, sink);
+                debug!(
+                    "Batch complete: {} events received, {} paths filtered, {} records stored",
+                    stats.received, stats.filtered, stats.stored
+                );
+            }
+
+            if events.iter().any(|event| is_config_change(&config, event)) {
+                control.request_reload();
+            }
+        }
+    }
+
+    /// Sends the projects (and their details) which had non-ignored changes to be stored
+    fn store_events(&self, events: &[DebouncedEvent], sink: &Sender<RawLog>) -> BatchStats {
+        let mut stats = BatchStats {
+            received: events.len(),
+            ..BatchStats::default()
+        };
+
+        let mut paths_per_project: HashMap<String, Vec<String>> = HashMap::new();
+        for path in events
+            .iter()
+            .flat_map(|event| self.get_paths_from_event(event))
+        {
+            if self.is_ignored(&path) {
+                stats.filtered += 1;
+                continue;
+            }
+            match self.extract_project_name(&path) {
+                Some(project) => {
+                    trace!("File change detected on {:?}", path);
+                    paths_per_project
+                        .entry(project)
+                        .or_default()
                         .push(path.to_string_lossy().into_owned());
+                }
+                None => stats.filtered += 1,
+            }
+        }
 
-                    acc
-                })
-                .into_iter()
-                .filter_map(|(project, paths)| {
-                    let dir = match &paths.get(0) {
-                        Some(path) => path.split(&project).next().unwrap().to_owned() + &project,
-                        None => panic!("This vec should never be empty"),
-                    };
-                    if git::contains_file_which_would_not_be_ignored(dir, &paths) {
-                        debug!("Found non-ignored changes for {:?}", project);
-                        Some(project)
-                    } else {
-                        debug!("All changes to {:?} were git ignored", project);
-                        None
-                    }
-                })
-                .for_each(|project| self.store_project(&project));
+        for (project, paths) in paths_per_project {
+            let dir = match paths.first() {
+                Some(path) => path.split(&project).next().unwrap().to_owned() + &project,
+                None => panic!("This vec should never be empty"),
+            };
+            // ignored files, like build output, shouldn't show up in the language and directory breakdowns
+            let changes = paths.len();
+            let paths = git::files_which_would_not_be_ignored(dir, &paths);
+            stats.filtered += changes - paths.len();
+            if paths.is_empty() {
+                debug!("All changes to {:?} were git ignored", project);
+                continue;
+            }
+            debug!("Found non-ignored changes for {:?}", project);
+
+            // store one record per language and directory so the project time can be split between them
+            let details: BTreeSet<(Option<String>, Option<String>)> = paths
+                .iter()
+                .map(|path| (language::classify(path), self.extract_project_dir(path)))
+                .collect();
+            for (language, dir) in details {
+                self.report_project(sink, &project, language, dir);
+                stats.stored += 1;
+            }
         }
+
+        stats
     }
 
     fn extract_project_name<T>(&self, path: T) -> Option<String>
@@ -97,29 +436,233 @@ impl<'a> TimeTracker<'a> {
         T: AsRef<Path>,
     {
         let path = path.as_ref();
-        let raw_data_file_path = PathBuf::from(&self.config.raw_data_path);
-        // TODO handle file system separators in platform independent way
-        if path != raw_data_file_path {
-            for track_path in &self.config.track_paths {
-                if let Ok(path) = path.strip_prefix(track_path) {
-                    return Some(
-                        path.components()
-                            .next()
-                            .expect("Path only contained root path")
-                            .as_os_str()
-                            .to_string_lossy()
-                            .to_string(),
-                    );
-                };
+        // changes to TimeTrack's own files are not activity on a project
+        if self.config.is_data_file(path) || self.config.is_user_config_file(path) {
+            return None;
+        }
+
+        match self.strip_track_path(path) {
+            Some(relative_path) => match relative_path.components().next() {
+                Some(project) => Some(project.as_os_str().to_string_lossy().to_string()),
+                None => {
+                    trace!("Skipping change to track path {:?}", path);
+                    None
+                }
+            },
+            None => {
+                // the watcher can report paths we don't recognize, for example through a symlink
+                // created after startup, so these are skipped rather than stopping the tracker
+                warn!(
+                    "Skipping change to {:?} which is not in the configured track paths",
+                    path
+                );
+                None
+            }
+        }
+    }
+
+    /// Returns the event paths, resolved so they can be matched against the track paths
+    fn get_paths_from_event(&self, event: &DebouncedEvent) -> Vec<PathBuf> {
+        match event {
+            DebouncedEvent::Create(path) |
+            DebouncedEvent::Write(path) |
+            DebouncedEvent::Chmod(path) |
+            DebouncedEvent::Remove(path) => { vec![self.resolve_path(path)] },
+            // a rename is activity on both projects, but a file moved into or out of
+            // the track paths is only activity on the side which is tracked
+            DebouncedEvent::Rename(from, to) => {
+                vec![self.resolve_path(from), self.resolve_path(to)]
+                    .into_iter()
+                    .filter(|path| self.strip_track_path(path).is_some())
+                    .collect()
+            },
+            DebouncedEvent::NoticeWrite(_) | // NoticeWrite and NoticeRemove both create duplicate entries for our use case
+            DebouncedEvent::NoticeRemove(_) |
+            DebouncedEvent::Rescan |
+            DebouncedEvent::Error(_, _) => { vec![] },
+        }
+    }
+
+    /// Returns the path as it is if it is within a track path, otherwise normalized
+    ///
+    /// A project in the track paths can be a symlink to a directory elsewhere, which normalizing
+    /// would resolve out of the track paths, so the path is only normalized when it doesn't match.
+    fn resolve_path(&self, path: &Path) -> PathBuf {
+        if self.strip_track_path(path).is_some() {
+            path.to_path_buf()
+        } else {
+            normalize_path(path)
+        }
+    }
+
+    /// Checks if the path is within a directory which the configuration ignores
+    fn is_ignored(&self, path: &Path) -> bool {
+        match self.strip_track_path(path) {
+            Some(relative_path) => self.config.ignore.is_ignored(&relative_path),
+            None => false,
+        }
+    }
+
+    /// Returns the path relative to the track path which contains it
+    fn strip_track_path(&self, path: &Path) -> Option<PathBuf> {
+        self.config
+            .track_paths
+            .iter()
+            .filter_map(|track_path| strip_path_prefix(path, track_path))
+            .next()
+    }
+
+    /// Returns the directory containing the path relative to its project, limited to the configured depth
+    fn extract_project_dir<T>(&self, path: T) -> Option<String>
+    where
+        T: AsRef<Path>,
+    {
+        self.relative_project_dir(path.as_ref().parent()?)
+    }
+
+    /// Returns the directory relative to its project, limited to the configured depth
+    ///
+    /// Files within `.git`, like a commit message open in an editor, are work on the project as a
+    /// whole rather than on a directory, so they are recorded on the project root.
+    fn relative_project_dir(&self, dir: &Path) -> Option<String> {
+        let dir = self.strip_track_path(dir)?;
+        // skip the project itself, since it is recorded separately
+        let dirs: Vec<String> = dir
+            .components()
+            .skip(1)
+            .take_while(|component| component.as_os_str() != ".git")
+            .take(self.config.dir_depth)
+            .map(|component| component.as_os_str().to_string_lossy().to_string())
+            .collect();
+
+        Some(if dirs.is_empty() {
+            String::from(".")
+        } else {
+            dirs.join("/")
+        })
+    }
+
+    /// Records activity on the project containing the path, such as a file open in an editor or a shell's working directory
+    ///
+    /// The activity is recorded at the given timestamp (seconds since the Unix epoch), or now.
+    pub fn ping<T>(&self, path: T, timestamp: Option<u64>)
+    where
+        T: AsRef<Path>,
+    {
+        let timestamp = timestamp.unwrap_or_else(now);
+        if let Some(log) = self.activity_from_path(&path, timestamp, None) {
+            return self.store_reported(log);
+        }
+
+        // without a selected profile, the path may be in the track paths of a named profile
+        if self.config.profile.is_some() {
+            return;
+        }
+        for name in &self.config.profile_names {
+            match self.config.for_profile(Some(name)) {
+                Ok(config) => {
+                    let tracker = TimeTracker::new(&config);
+                    if let Some(log) = tracker.activity_from_path(&path, timestamp, None) {
+                        return tracker.store_reported(log);
+                    }
+                }
+                Err(err) => warn!("Failed to read the {} profile ({})", name, err),
             }
+        }
+    }
+
+    /// Records activity on the project by name, for integrations which don't work with files in the track paths
+    pub fn ping_project(
+        &self,
+        project: &str,
+        timestamp: Option<u64>,
+    ) -> Result<(), TimeTrackerError> {
+        let log = activity_for_project(project, timestamp.unwrap_or_else(now))?;
+        self.store_reported(log);
+
+        Ok(())
+    }
 
-            panic!("Failed processing path which was not in configured track paths");
+    /// Stores activity reported outside of the tracker, such as pings from the shell hook, the
+    /// same way as the activity the tracker receives from its sources
+    fn store_reported(&self, log: RawLog) {
+        if self.is_tracking_paused() {
+            debug!(
+                "Not storing activity on {} while tracking is paused",
+                log.name
+            );
+            return;
         }
+        if let Some(log) = self.during_working_hours(log) {
+            self.store(&log);
+        }
+    }
+
+    /// Resolves activity reported on a path to its project, the same way as file changes
+    ///
+    /// Unlike file changes, this doesn't check whether the path is git ignored, since working
+    /// in a directory like `target` (running tests, reading logs) is still work on the project.
+    /// The language is classified from the path unless it is given.
+    pub(crate) fn activity_from_path<T>(
+        &self,
+        path: T,
+        timestamp: u64,
+        language: Option<String>,
+    ) -> Option<RawLog>
+    where
+        T: AsRef<Path>,
+    {
+        let path = self.resolve_path(path.as_ref());
+        // activity is often reported from outside of the track paths, which isn't worth a warning each time
+        if self.strip_track_path(&path).is_none() || self.is_ignored(&path) {
+            debug!("Skipping activity on {:?}", path);
+            return None;
+        }
+
+        let project = self.extract_project_name(&path)?;
+        let (language, dir) = if path.is_dir() {
+            (language, self.relative_project_dir(&path))
+        } else {
+            (
+                language.or_else(|| language::classify(&path)),
+                self.extract_project_dir(&path),
+            )
+        };
 
-        None
+        Some(RawLog {
+            name: project,
+            timestamp,
+            language,
+            dir,
+            tag: None,
+        })
     }
 
-    fn store_project(&self, project_name: &str) {
+    fn report_project(
+        &self,
+        sink: &Sender<RawLog>,
+        project_name: &str,
+        language: Option<String>,
+        dir: Option<String>,
+    ) {
+        let log = RawLog {
+            name: project_name.to_string(),
+            timestamp: now(),
+            language,
+            dir,
+            tag: None,
+        };
+        sink.send(log)
+            .unwrap_or_else(|_| error!("Failed to report activity, the tracker has stopped"));
+    }
+
+    fn store(&self, log: &RawLog) {
+        // a line which can't be read back would break every report
+        if let Err(err) = log.validate() {
+            error!("Not storing invalid activity ({})", err);
+            return;
+        }
+
         let mut file = OpenOptions::new()
             .read(true)
             .write(true)
@@ -127,35 +670,166 @@ impl<'a> TimeTracker<'a> {
             .append(true)
             .open(&self.config.raw_data_path)
             .unwrap();
-        let time = SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
 
-        let log = format!("{}/{}", project_name, time);
         debug!("Log stored: {}", log);
-        writeln!(&mut file, "{}", log).unwrap_or_else(|_| error!("Failed to write raw data"));
+        // a single write, so lines stored at the same time by the tracker and pings aren't interleaved
+        file.write_all(format!("{}\n", log).as_bytes())
+            .unwrap_or_else(|_| error!("Failed to write raw data"));
+    }
+}
+
+/// Activity on the project by name, which must be valid in the raw data
+pub(crate) fn activity_for_project(
+    project: &str,
+    timestamp: u64,
+) -> Result<RawLog, TimeTrackerError> {
+    // the raw data is separated by slashes and newlines
+    if project.is_empty() || project.contains(&['/', '\n'][..]) {
+        return Err(TimeTrackerError::InvalidProjectError(project.to_string()));
+    }
+
+    Ok(RawLog {
+        name: project.to_string(),
+        timestamp,
+        language: None,
+        dir: None,
+        tag: None,
+    })
+}
+
+/// The current time as seconds since the Unix epoch, the resolution of the raw data
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// Waits up to `timeout` for an event, then collects the events which arrive within `batch_window` of it
+///
+/// Disconnection is only returned when no events were received, so the last batch is still stored.
+/// The batch is cut short when the source is shut down, so it can be stored before exiting.
+fn receive_batch(
+    rx: &Receiver<DebouncedEvent>,
+    watchers: &mut WatcherSet,
+    timeout: Duration,
+    batch_window: Duration,
+    control: &SourceControl,
+) -> Result<Vec<DebouncedEvent>, RecvTimeoutError> {
+    let mut events = vec![];
+    add_to_batch(watchers, rx.recv_timeout(timeout)?, &mut events);
+    let first_record_time = Instant::now();
+
+    while !control.is_shutting_down() {
+        let remaining = match batch_window.checked_sub(first_record_time.elapsed()) {
+            Some(remaining) if remaining > Duration::from_secs(0) => remaining,
+            _ => break,
+        };
+        match rx.recv_timeout(remaining.min(CONTROL_CHECK_INTERVAL)) {
+            Ok(event) => add_to_batch(watchers, event, &mut events),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    Ok(events)
+}
+
+/// Handles the event as soon as it is received, rather than when the batch is complete, so a new
+/// directory is watched before more files are created in it
+///
+/// Files created in a new directory before it was watched are added to the batch as if their
+/// events had been received.
+fn add_to_batch(
+    watchers: &mut WatcherSet,
+    event: DebouncedEvent,
+    events: &mut Vec<DebouncedEvent>,
+) {
+    let files = handle_watcher_event(watchers, &event);
+    events.push(event);
+    events.extend(files.into_iter().map(DebouncedEvent::Create));
+}
+
+fn is_config_change(config: &Configuration, event: &DebouncedEvent) -> bool {
+    match event {
+        DebouncedEvent::Create(path)
+        | DebouncedEvent::Write(path)
+        | DebouncedEvent::Remove(path)
+        | DebouncedEvent::Rename(_, path) => config.is_user_config_file(&normalize_path(path)),
+        _ => false,
+    }
+}
+
+/// Reloads the configuration used to store activity and gives it to every source, keeping the
+/// previous configuration if the new one is invalid
+fn reload_config(
+    config: &mut Configuration,
+    state: &Mutex<TrackerState>,
+    controls: &[SourceControl],
+) {
+    match config.reload() {
+        Ok(new_config) => {
+            info!("Configuration reloaded");
+            state
+                .lock()
+                .expect("Failed to lock tracker state")
+                .set_track_paths(&new_config.track_paths);
+            for control in controls {
+                control.reload(new_config.clone());
+            }
+            *config = new_config;
+        }
+        Err(err) => error!(
+            "Failed to reload configuration, keeping the previous configuration ({})",
+            err
+        ),
     }
 }
 
-fn get_path_from_event(event: &DebouncedEvent) -> Option<&Path> {
+/// Re-establishes watches when the watcher reports it may have missed changes or the track path was removed,
+/// and keeps the watched directories up to date as directories are created and removed
+///
+/// Returns the files which were already in a newly created directory when it was watched.
+fn handle_watcher_event(watchers: &mut WatcherSet, event: &DebouncedEvent) -> Vec<PathBuf> {
     match event {
-        DebouncedEvent::Create(path) |
-        DebouncedEvent::Write(path) |
-        DebouncedEvent::Chmod(path) |
-        DebouncedEvent::Remove(path) |
-        DebouncedEvent::Rename(_, path) => { Some(path.as_ref()) }, // TODO use both paths from rename?
-        DebouncedEvent::NoticeWrite(_) | // NoticeWrite and NoticeRemove both create duplicate entries for our use case
-        DebouncedEvent::NoticeRemove(_) |
-        DebouncedEvent::Rescan |
-        DebouncedEvent::Error(_, _) => { None },
+        DebouncedEvent::Rescan => {
+            warn!("Watcher requested a rescan");
+            watchers.rewatch_all();
+        }
+        DebouncedEvent::Error(err, path) => {
+            error!("Watcher error {} on {:?}", err, path);
+            match path.as_ref().and_then(|path| watchers.track_path_of(path)) {
+                Some(track_path) => watchers.rewatch(&track_path),
+                None => watchers.rewatch_all(),
+            }
+        }
+        DebouncedEvent::Remove(path) if watchers.is_watching(path) => {
+            watchers.mark_failed(path);
+        }
+        DebouncedEvent::Create(path) if path.is_dir() => return watchers.watch_new_dir(path),
+        DebouncedEvent::Remove(path) => watchers.unwatch_dir(path),
+        DebouncedEvent::Rename(from, to) => {
+            watchers.unwatch_dir(from);
+            // the files in a moved directory weren't changed, so they aren't activity
+            if to.is_dir() {
+                watchers.watch_new_dir(to);
+            }
+        }
+        _ => {}
     }
+    vec![]
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::Configuration;
+    use crate::calc::raw_log::raw_logs_from;
+    use crate::config::hours::WorkingHours;
+    use crate::config::{get_config_with, ConfigOverrides};
+    use crate::source::ReplaySource;
+    use std::fs;
+    use std::process::Command;
+    use tempfile::tempdir;
 
     #[test]
     fn extract_project_name_some() {
@@ -207,6 +881,709 @@ mod tests {
         assert_eq!(None, tracker.extract_project_name(event_path));
     }
 
+    #[test]
+    fn extract_project_name_not_in_track_paths() {
+        let config = get_mock_config();
+        let event_path = PathBuf::from("/Users/josh/Downloads/file1.rs");
+
+        let tracker = TimeTracker::new(&config);
+
+        assert_eq!(None, tracker.extract_project_name(event_path));
+    }
+
+    #[test]
+    fn extract_project_name_track_path_itself() {
+        let config = get_mock_config();
+        let event_path = config.track_paths[0].clone();
+
+        let tracker = TimeTracker::new(&config);
+
+        assert_eq!(None, tracker.extract_project_name(event_path));
+    }
+
+    #[test]
+    fn is_ignored() {
+        let config = get_mock_config();
+
+        let tracker = TimeTracker::new(&config);
+
+        assert!(tracker.is_ignored(&config.track_paths[0].join("testProj/target/debug/testProj")));
+        assert!(!tracker.is_ignored(&config.track_paths[0].join("testProj/src/main.rs")));
+    }
+
+    #[test]
+    fn extract_project_dir_nested() {
+        let mut config = get_mock_config();
+        config.dir_depth = 2;
+        let event_path = config.track_paths[0].join("testProj/crates/core/src/lib.rs");
+
+        let tracker = TimeTracker::new(&config);
+
+        assert_eq!(
+            Some("crates/core".to_string()),
+            tracker.extract_project_dir(event_path)
+        );
+    }
+
+    #[test]
+    fn extract_project_dir_depth_limited() {
+        let config = get_mock_config();
+        let event_path = config.track_paths[0].join("testProj/crates/core/src/lib.rs");
+
+        let tracker = TimeTracker::new(&config);
+
+        assert_eq!(
+            Some("crates".to_string()),
+            tracker.extract_project_dir(event_path)
+        );
+    }
+
+    #[test]
+    fn extract_project_dir_git_dir() {
+        let config = get_mock_config();
+        let event_path = config.track_paths[0].join("testProj/.git/COMMIT_EDITMSG");
+
+        let tracker = TimeTracker::new(&config);
+
+        assert_eq!(
+            Some(".".to_string()),
+            tracker.extract_project_dir(event_path)
+        );
+    }
+
+    #[test]
+    fn extract_project_dir_project_root() {
+        let config = get_mock_config();
+        let event_path = config.track_paths[1].join("testOtherProj/Cargo.toml");
+
+        let tracker = TimeTracker::new(&config);
+
+        assert_eq!(
+            Some(".".to_string()),
+            tracker.extract_project_dir(event_path)
+        );
+    }
+
+    #[test]
+    fn ping_stores_project_and_dir() {
+        let dir = tempdir().unwrap();
+        let config = get_temp_config(dir.path());
+        let project_dir = config.track_paths[0].join("testProj/src");
+        fs::create_dir_all(&project_dir).unwrap();
+
+        TimeTracker::new(&config).ping(&project_dir, None);
+
+        let raw_data = fs::read_to_string(&config.raw_data_path).unwrap();
+        let raw_logs = raw_logs_from(&raw_data).unwrap();
+        assert_eq!(1, raw_logs.len());
+        assert_eq!("testProj", raw_logs[0].name);
+        assert_eq!(None, raw_logs[0].language);
+        assert_eq!(Some("src".to_string()), raw_logs[0].dir);
+    }
+
+    #[test]
+    fn ping_file_with_timestamp() {
+        let dir = tempdir().unwrap();
+        let config = get_temp_config(dir.path());
+        let file = config.track_paths[0].join("testProj/src/main.rs");
+
+        TimeTracker::new(&config).ping(&file, Some(100));
+
+        let raw_data = fs::read_to_string(&config.raw_data_path).unwrap();
+        assert_eq!("testProj/100/Rust/src\n", raw_data);
+    }
+
+    #[test]
+    fn ping_project() {
+        let dir = tempdir().unwrap();
+        let config = get_temp_config(dir.path());
+
+        let tracker = TimeTracker::new(&config);
+        tracker.ping_project("testProj", Some(100)).unwrap();
+        assert!(tracker.ping_project("test/Proj", None).is_err());
+        assert!(tracker.ping_project("", None).is_err());
+
+        let raw_data = fs::read_to_string(&config.raw_data_path).unwrap();
+        assert_eq!("testProj/100\n", raw_data);
+    }
+
+    #[test]
+    fn ping_outside_projects() {
+        let dir = tempdir().unwrap();
+        let config = get_temp_config(dir.path());
+        let track_path = config.track_paths[0].clone();
+
+        let tracker = TimeTracker::new(&config);
+        tracker.ping(dir.path(), None);
+        tracker.ping(&track_path, None);
+        tracker.ping(track_path.join("testProj/node_modules"), None);
+
+        assert!(!config.raw_data_path.exists());
+    }
+
+    #[test]
+    fn get_paths_from_rename_between_projects() {
+        let config = get_mock_config();
+        let from = config.track_paths[0].join("testProj/file1.rs");
+        let to = config.track_paths[1].join("testOtherProj/file1.rs");
+        let event = DebouncedEvent::Rename(from.clone(), to.clone());
+
+        let tracker = TimeTracker::new(&config);
+        let paths = tracker.get_paths_from_event(&event);
+
+        assert_eq!(vec![from, to], paths);
+        assert_eq!(
+            vec![
+                Some("testProj".to_string()),
+                Some("testOtherProj".to_string())
+            ],
+            paths
+                .into_iter()
+                .map(|path| tracker.extract_project_name(path))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn get_paths_from_rename_out_of_track_paths() {
+        let config = get_mock_config();
+        let from = config.track_paths[0].join("testProj/file1.rs");
+        let to = PathBuf::from("/tmp/file1.rs");
+        let event = DebouncedEvent::Rename(from.clone(), to);
+
+        let tracker = TimeTracker::new(&config);
+
+        assert_eq!(vec![from], tracker.get_paths_from_event(&event));
+    }
+
+    #[test]
+    fn get_paths_from_rename_into_track_paths() {
+        let config = get_mock_config();
+        let from = PathBuf::from("/tmp/file1.rs");
+        let to = config.track_paths[0].join("testProj/file1.rs");
+        let event = DebouncedEvent::Rename(from, to.clone());
+
+        let tracker = TimeTracker::new(&config);
+
+        assert_eq!(vec![to], tracker.get_paths_from_event(&event));
+    }
+
+    #[test]
+    fn handle_watcher_event_track_path_removed() {
+        let config = get_mock_config();
+        let dir = tempdir().unwrap();
+        let track_path = dir.path().join("Projects");
+        fs::create_dir(&track_path).unwrap();
+        let (tx, _rx) = channel();
+        let mut watchers = WatcherSet::new(tx, config.watcher_options());
+        watchers.watch(&track_path);
+
+        fs::remove_dir(&track_path).unwrap();
+        handle_watcher_event(&mut watchers, &DebouncedEvent::Remove(track_path.clone()));
+        assert!(!watchers.is_watching(&track_path));
+
+        fs::create_dir(&track_path).unwrap();
+        handle_watcher_event(&mut watchers, &DebouncedEvent::Rescan);
+        assert!(watchers.is_watching(&track_path));
+    }
+
+    #[test]
+    fn receive_batch_collects_events_within_window() {
+        let (tx, rx) = channel();
+        tx.send(DebouncedEvent::Rescan).unwrap();
+        tx.send(DebouncedEvent::Rescan).unwrap();
+
+        let events = receive_batch(
+            &rx,
+            &mut get_mock_watchers(),
+            Duration::from_secs(1),
+            Duration::from_millis(50),
+            &SourceControl::new(),
+        );
+
+        assert_eq!(2, events.unwrap().len());
+    }
+
+    #[test]
+    fn receive_batch_watches_new_directories() {
+        let dir = tempdir().unwrap();
+        let track_path = dir.path().join("Projects");
+        fs::create_dir(&track_path).unwrap();
+        let mut watchers = get_mock_watchers();
+        watchers.watch(&track_path);
+
+        // the file is created before the new directory is watched
+        let project_dir = track_path.join("testProj");
+        fs::create_dir(&project_dir).unwrap();
+        fs::write(project_dir.join("main.rs"), "").unwrap();
+        let (tx, rx) = channel();
+        tx.send(DebouncedEvent::Create(project_dir.clone()))
+            .unwrap();
+
+        let events = receive_batch(
+            &rx,
+            &mut watchers,
+            Duration::from_secs(1),
+            Duration::from_millis(50),
+            &SourceControl::new(),
+        )
+        .unwrap();
+
+        assert!(events.contains(&DebouncedEvent::Create(project_dir.join("main.rs"))));
+    }
+
+    #[test]
+    fn receive_batch_timeout() {
+        let (_tx, rx) = channel();
+
+        assert_eq!(
+            Err(RecvTimeoutError::Timeout),
+            receive_batch(
+                &rx,
+                &mut get_mock_watchers(),
+                Duration::from_millis(10),
+                Duration::from_millis(10),
+                &SourceControl::new()
+            )
+            .map(|events| events.len())
+        );
+    }
+
+    #[test]
+    fn receive_batch_disconnected_after_last_batch() {
+        let (tx, rx) = channel();
+        tx.send(DebouncedEvent::Rescan).unwrap();
+        drop(tx);
+
+        let events = receive_batch(
+            &rx,
+            &mut get_mock_watchers(),
+            Duration::from_secs(1),
+            Duration::from_secs(1),
+            &SourceControl::new(),
+        );
+        assert_eq!(1, events.unwrap().len());
+
+        assert_eq!(
+            Err(RecvTimeoutError::Disconnected),
+            receive_batch(
+                &rx,
+                &mut get_mock_watchers(),
+                Duration::from_secs(1),
+                Duration::from_secs(1),
+                &SourceControl::new()
+            )
+            .map(|events| events.len())
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_project() {
+        let dir = tempdir().unwrap();
+        let config = get_temp_config(dir.path());
+        let track_path = &config.track_paths[0];
+        fs::create_dir_all(dir.path().join("Elsewhere/testProj/src")).unwrap();
+        fs::create_dir_all(track_path).unwrap();
+        std::os::unix::fs::symlink(
+            dir.path().join("Elsewhere/testProj"),
+            track_path.join("testProj"),
+        )
+        .unwrap();
+        let tracker = TimeTracker::new(&config);
+
+        let path = track_path.join("testProj/src/main.rs");
+        let paths = tracker.get_paths_from_event(&DebouncedEvent::Write(path.clone()));
+        assert_eq!(vec![path], paths);
+        assert_eq!(
+            Some("testProj".to_string()),
+            tracker.extract_project_name(&paths[0])
+        );
+
+        tracker.ping(track_path.join("testProj/src"), Some(100));
+        let raw_data = fs::read_to_string(&config.raw_data_path).unwrap();
+        assert_eq!("testProj", raw_logs_from(&raw_data).unwrap()[0].name);
+    }
+
+    #[test]
+    fn store_events_stats() {
+        let dir = tempdir().unwrap();
+        let config = get_temp_config(dir.path());
+        let track_path = &config.track_paths[0];
+        let events = vec![
+            DebouncedEvent::Write(track_path.join("testProj/src/main.rs")),
+            DebouncedEvent::Write(track_path.join("testProj/src/lib.rs")),
+            DebouncedEvent::Write(track_path.join("testProj/target/debug/testProj")),
+            DebouncedEvent::Write(dir.path().join("file1.rs")),
+            DebouncedEvent::NoticeWrite(track_path.join("testProj/src/main.rs")),
+        ];
+
+        let (sink, _logs) = channel();
+
+        let stats = TimeTracker::new(&config).store_events(&events, &sink);
+
+        assert_eq!(
+            BatchStats {
+                received: 5,
+                filtered: 2,
+                stored: 1,
+            },
+            stats
+        );
+    }
+
+    #[test]
+    fn store_events_skips_git_ignored_files() {
+        let dir = tempdir().unwrap();
+        let config = get_temp_config(dir.path());
+        let project_dir = config.track_paths[0].join("testProj");
+        fs::create_dir_all(project_dir.join("src")).unwrap();
+        Command::new("git")
+            .arg("init")
+            .current_dir(&project_dir)
+            .output()
+            .unwrap();
+        fs::write(project_dir.join(".gitignore"), "*.log\n/target\n").unwrap();
+        let events = vec![
+            DebouncedEvent::Write(project_dir.join("src/main.rs")),
+            DebouncedEvent::Write(project_dir.join("build.log")),
+            DebouncedEvent::Write(project_dir.join("target/debug/testProj")),
+            DebouncedEvent::Write(project_dir.join(".git/hooks/pre-commit.sample")),
+        ];
+
+        let (sink, logs) = channel();
+        let stats = TimeTracker::new(&config).store_events(&events, &sink);
+
+        // only the changes which aren't ignored are split into languages and directories
+        assert_eq!(3, stats.filtered);
+        let log = logs.try_recv().unwrap();
+        assert_eq!(Some("Rust".to_string()), log.language);
+        assert_eq!(Some("src".to_string()), log.dir);
+        assert!(logs.try_recv().is_err());
+    }
+
+    #[test]
+    fn track_events_until_disconnected() {
+        let dir = tempdir().unwrap();
+        let mut config = get_temp_config(dir.path());
+        config.batch_window = Duration::from_millis(10);
+        let track_path = config.track_paths[0].clone();
+        let (tx, rx) = channel();
+        // the watchers get their own channel, otherwise the synthetic channel never disconnects
+        let (watcher_tx, _watcher_rx) = channel();
+        let mut watchers = WatcherSet::new(watcher_tx, config.watcher_options());
+
+        tx.send(DebouncedEvent::Write(
+            track_path.join("testProj/src/main.rs"),
+        ))
+        .unwrap();
+        tx.send(DebouncedEvent::Write(
+            track_path.join("testOtherProj/README.md"),
+        ))
+        .unwrap();
+        drop(tx);
+        let (sink, logs) = channel();
+        TimeTracker::new(&config).track_events(&rx, &mut watchers, &sink, &SourceControl::new());
+        drop(sink);
+
+        let mut projects: Vec<String> = logs.iter().map(|log| log.name).collect();
+        projects.sort();
+        assert_eq!(vec!["testOtherProj", "testProj"], projects);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn track_sources_paused() {
+        let dir = tempdir().unwrap();
+        let config = get_temp_config(dir.path());
+        let sources: Vec<Box<dyn ActivitySource>> = vec![Box::new(PausingSource {
+            config: config.clone(),
+        })];
+
+        TimeTracker::new(&config).track_sources(sources).unwrap();
+
+        let raw_data = fs::read_to_string(&config.raw_data_path).unwrap();
+        assert_eq!("testProj/100\n", raw_data);
+    }
+
+    /// Pauses the tracker through the control socket between activity
+    #[cfg(unix)]
+    struct PausingSource {
+        config: Configuration,
+    }
+
+    #[cfg(unix)]
+    impl ActivitySource for PausingSource {
+        fn name(&self) -> String {
+            String::from("pausing")
+        }
+
+        fn run(self: Box<Self>, sink: Sender<RawLog>, _control: SourceControl) {
+            let tracker = TimeTracker::new(&self.config);
+
+            sink.send(get_mock_log("testProj", 100)).unwrap();
+            // the control socket starts after the sources, and the first activity is stored before pausing
+            while !self.config.raw_data_path.exists() || tracker.pause(None).is_err() {
+                thread::sleep(Duration::from_millis(10));
+            }
+            sink.send(get_mock_log("testProj", 200)).unwrap();
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn track_sources_stopped() {
+        let dir = tempdir().unwrap();
+        let config = get_temp_config(dir.path());
+        let sources: Vec<Box<dyn ActivitySource>> = vec![Box::new(StoppingSource {
+            config: config.clone(),
+        })];
+        let start = Instant::now();
+
+        let tracker = TimeTracker::new(&config);
+        tracker.track_sources(sources).unwrap();
+
+        // the source keeps running for longer than this, so only stopping ends tracking
+        assert!(start.elapsed() < Duration::from_secs(30));
+        assert!(!config.lock_file_path().exists());
+    }
+
+    /// Stops the tracker through the control socket, then keeps running
+    #[cfg(unix)]
+    struct StoppingSource {
+        config: Configuration,
+    }
+
+    #[cfg(unix)]
+    impl ActivitySource for StoppingSource {
+        fn name(&self) -> String {
+            String::from("stopping")
+        }
+
+        fn run(self: Box<Self>, _sink: Sender<RawLog>, _control: SourceControl) {
+            let tracker = TimeTracker::new(&self.config);
+            // the control socket starts after the sources
+            while tracker.stop().is_err() {
+                thread::sleep(Duration::from_millis(10));
+            }
+            thread::sleep(Duration::from_secs(60));
+        }
+    }
+
+    #[test]
+    fn track_sources_reloads_configuration() {
+        let dir = tempdir().unwrap();
+        let user_config_path = dir.path().join("timetrack_config");
+        fs::write(&user_config_path, "track_paths = []\n").unwrap();
+        let config = get_config_with(&ConfigOverrides {
+            config_path: Some(user_config_path.clone()),
+            data_dir: Some(dir.path().to_path_buf()),
+            profile: None,
+        })
+        .unwrap();
+        let sources: Vec<Box<dyn ActivitySource>> = vec![Box::new(ReloadingSource {
+            config: config.clone(),
+            user_config_path,
+        })];
+
+        TimeTracker::new(&config).track_sources(sources).unwrap();
+
+        let raw_data = fs::read_to_string(&config.raw_data_path).unwrap();
+        assert_eq!("testProj/100\n", raw_data);
+    }
+
+    /// Removes every working hour from the configuration between activity, and has it reloaded
+    struct ReloadingSource {
+        config: Configuration,
+        user_config_path: PathBuf,
+    }
+
+    impl ActivitySource for ReloadingSource {
+        fn name(&self) -> String {
+            String::from("reloading")
+        }
+
+        fn run(self: Box<Self>, sink: Sender<RawLog>, control: SourceControl) {
+            sink.send(get_mock_log("testProj", 100)).unwrap();
+            while !self.config.raw_data_path.exists() {
+                thread::sleep(Duration::from_millis(10));
+            }
+
+            fs::write(
+                &self.user_config_path,
+                "track_paths = []\n\n[working_hours]\n",
+            )
+            .unwrap();
+            control.request_reload();
+            while control.take_reloaded_config().is_none() {
+                thread::sleep(Duration::from_millis(10));
+            }
+            sink.send(get_mock_log("testProj", 200)).unwrap();
+        }
+    }
+
+    #[test]
+    fn track_sources_stores_activity_from_every_source() {
+        let dir = tempdir().unwrap();
+        let config = get_temp_config(dir.path());
+        let sources: Vec<Box<dyn ActivitySource>> = vec![
+            Box::new(ReplaySource::new(vec![get_mock_log("testProj", 100)])),
+            Box::new(ReplaySource::new(vec![get_mock_log("testOtherProj", 200)])),
+        ];
+
+        TimeTracker::new(&config).track_sources(sources).unwrap();
+
+        let raw_data = fs::read_to_string(&config.raw_data_path).unwrap();
+        let mut lines: Vec<&str> = raw_data.lines().collect();
+        lines.sort();
+        assert_eq!(vec!["testOtherProj/200", "testProj/100"], lines);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn ping_while_paused() {
+        let dir = tempdir().unwrap();
+        let config = get_temp_config(dir.path());
+        let project_dir = config.track_paths[0].join("testProj/src");
+        fs::create_dir_all(&project_dir).unwrap();
+        let state = Arc::new(Mutex::new(TrackerState::new(&config.track_paths)));
+        let _socket = ControlSocket::listen(&config, state).unwrap();
+        let tracker = TimeTracker::new(&config);
+
+        tracker.pause(None).unwrap();
+        tracker.ping(&project_dir, Some(100));
+        tracker.ping_project("testOtherProj", Some(200)).unwrap();
+        assert!(!config.raw_data_path.exists());
+
+        tracker.resume().unwrap();
+        tracker.ping_project("testOtherProj", Some(300)).unwrap();
+        assert_eq!(
+            "testOtherProj/300\n",
+            fs::read_to_string(&config.raw_data_path).unwrap()
+        );
+    }
+
+    #[test]
+    fn during_working_hours() {
+        let mut config = get_mock_config();
+        let log = || get_mock_log("testProj", 100);
+
+        assert_eq!(
+            Some(log()),
+            TimeTracker::new(&config).during_working_hours(log())
+        );
+
+        // without any ranges, every time is outside of the working hours
+        config.working_hours = WorkingHours::new(Some(&toml::from_str("").unwrap())).unwrap();
+        assert_eq!(None, TimeTracker::new(&config).during_working_hours(log()));
+
+        config.working_hours =
+            WorkingHours::new(Some(&toml::from_str(r#"off_hours = "tag""#).unwrap())).unwrap();
+        assert_eq!(
+            Some(RawLog {
+                tag: Some(String::from("off-hours")),
+                ..log()
+            }),
+            TimeTracker::new(&config).during_working_hours(log())
+        );
+    }
+
+    #[test]
+    fn ping_outside_working_hours() {
+        let dir = tempdir().unwrap();
+        let mut config = get_temp_config(dir.path());
+        let project_dir = config.track_paths[0].join("testProj");
+        fs::create_dir_all(&project_dir).unwrap();
+        config.working_hours = WorkingHours::new(Some(&toml::from_str("").unwrap())).unwrap();
+
+        let tracker = TimeTracker::new(&config);
+        tracker.ping(&project_dir, Some(100));
+        tracker.ping_project("testOtherProj", Some(200)).unwrap();
+
+        assert_eq!(
+            "",
+            fs::read_to_string(&config.raw_data_path).unwrap_or_default()
+        );
+
+        config.working_hours =
+            WorkingHours::new(Some(&toml::from_str(r#"off_hours = "tag""#).unwrap())).unwrap();
+        let tracker = TimeTracker::new(&config);
+        tracker.ping(&project_dir, Some(100));
+        tracker.ping_project("testOtherProj", Some(200)).unwrap();
+
+        let raw_data = fs::read_to_string(&config.raw_data_path).unwrap();
+        let logs: Vec<_> = raw_logs_from(&raw_data)
+            .unwrap()
+            .into_iter()
+            .map(|log| (log.name, log.tag))
+            .collect();
+        let off_hours = Some(String::from("off-hours"));
+        assert_eq!(
+            vec![
+                (String::from("testProj"), off_hours.clone()),
+                (String::from("testOtherProj"), off_hours),
+            ],
+            logs
+        );
+    }
+
+    #[test]
+    fn track_sources_outside_working_hours() {
+        let dir = tempdir().unwrap();
+        let mut config = get_temp_config(dir.path());
+        // without any ranges, every time is outside of the working hours
+        config.working_hours = WorkingHours::new(Some(&toml::from_str("").unwrap())).unwrap();
+        let sources: Vec<Box<dyn ActivitySource>> =
+            vec![Box::new(ReplaySource::new(vec![get_mock_log(
+                "testProj", 100,
+            )]))];
+
+        TimeTracker::new(&config).track_sources(sources).unwrap();
+
+        assert_eq!(
+            "",
+            fs::read_to_string(&config.raw_data_path).unwrap_or_default()
+        );
+
+        config.working_hours =
+            WorkingHours::new(Some(&toml::from_str(r#"off_hours = "tag""#).unwrap())).unwrap();
+        let sources: Vec<Box<dyn ActivitySource>> =
+            vec![Box::new(ReplaySource::new(vec![get_mock_log(
+                "testProj", 100,
+            )]))];
+
+        TimeTracker::new(&config).track_sources(sources).unwrap();
+
+        assert_eq!(
+            "testProj/100#off-hours\n",
+            fs::read_to_string(&config.raw_data_path).unwrap()
+        );
+    }
+
+    fn get_mock_watchers() -> WatcherSet {
+        let (tx, _rx) = channel();
+        WatcherSet::new(tx, get_mock_config().watcher_options())
+    }
+
+    fn get_mock_log(name: &str, timestamp: u64) -> RawLog {
+        RawLog {
+            name: name.to_string(),
+            timestamp,
+            language: None,
+            dir: None,
+            tag: None,
+        }
+    }
+
+    /// A configuration writing raw data to the directory, whose track path does not exist so git is not consulted
+    fn get_temp_config(dir: &Path) -> Configuration {
+        Configuration::new_mock_config(
+            vec![normalize_path(dir).join("Projects")],
+            normalize_path(dir).join(".timetrack_raw"),
+            normalize_path(dir).join(".timetrack_processed"),
+        )
+    }
+
     fn get_mock_config() -> Configuration {
         Configuration::new_mock_config(
             vec![
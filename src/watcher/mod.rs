@@ -1,15 +1,223 @@
-use notify;
-use notify::{DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+use crate::config::ignore::IgnoreRules;
+use log::{log, warn};
+use notify::{DebouncedEvent, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+use serde_derive::{Deserialize, Serialize};
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::path::Path;
 use std::path::PathBuf;
 use std::sync::mpsc::Sender;
 use std::time::Duration;
 
+mod pruned;
+use self::pruned::PrunedWatcher;
+
+mod set;
+pub use self::set::WatcherSet;
+
+/// The mechanism used to detect file system changes
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum WatcherBackend {
+    /// The operating system's file system notifications (inotify, FSEvents, etc.)
+    Native,
+    /// Periodically scanning the track path, which works on network file systems
+    Poll,
+}
+
+impl Display for WatcherBackend {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            WatcherBackend::Native => write!(f, "native"),
+            WatcherBackend::Poll => write!(f, "poll"),
+        }
+    }
+}
+
+/// The configuration shared by the watchers for every track path
+#[derive(Clone, PartialEq, Debug)]
+pub struct WatcherOptions {
+    pub backend: WatcherBackend,
+    pub poll_interval: Duration,
+    pub ignore: IgnoreRules,
+}
+
+/// A watcher for a single track path, which stops watching when dropped
+#[allow(dead_code)] // the watchers are only held so they aren't dropped
+pub enum TrackWatcher {
+    Native(RecommendedWatcher),
+    Pruned(PrunedWatcher),
+    Poll(PollWatcher),
+}
+
+impl TrackWatcher {
+    pub fn backend(&self) -> WatcherBackend {
+        match self {
+            TrackWatcher::Native(_) | TrackWatcher::Pruned(_) => WatcherBackend::Native,
+            TrackWatcher::Poll(_) => WatcherBackend::Poll,
+        }
+    }
+
+    /// The number of directories watched individually, if directories are watched individually
+    pub fn watched_dir_count(&self) -> Option<usize> {
+        match self {
+            TrackWatcher::Pruned(watcher) => Some(watcher.watched_dirs().len()),
+            TrackWatcher::Native(_) | TrackWatcher::Poll(_) => None,
+        }
+    }
+}
+
 pub fn get_watcher(
     track_path: &PathBuf,
     tx: Sender<DebouncedEvent>,
-) -> Result<RecommendedWatcher, notify::Error> {
+    options: &WatcherOptions,
+) -> Result<TrackWatcher, notify::Error> {
+    match options.backend {
+        WatcherBackend::Native => match get_native_watcher(track_path, tx.clone(), &options.ignore)
+        {
+            Ok(watcher) => Ok(watcher),
+            Err(ref err) if is_watch_limit_error(err) => {
+                warn!(
+                    "Watch limit reached for path {:?}, falling back to polling",
+                    track_path
+                );
+                get_poll_watcher(track_path, tx, options.poll_interval).map(TrackWatcher::Poll)
+            }
+            Err(err) => Err(err),
+        },
+        WatcherBackend::Poll => {
+            get_poll_watcher(track_path, tx, options.poll_interval).map(TrackWatcher::Poll)
+        }
+    }
+}
+
+fn get_native_watcher(
+    track_path: &PathBuf,
+    tx: Sender<DebouncedEvent>,
+    ignore: &IgnoreRules,
+) -> Result<TrackWatcher, notify::Error> {
+    // inotify uses a watch per directory, so ignored directories are pruned to save watches,
+    // while other platforms watch the whole tree at once
+    if cfg!(target_os = "linux") && !ignore.is_empty() {
+        return PrunedWatcher::new(track_path, tx, ignore).map(TrackWatcher::Pruned);
+    }
+
     let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(0))?;
     watcher.watch(track_path, RecursiveMode::Recursive)?;
 
+    Ok(TrackWatcher::Native(watcher))
+}
+
+fn get_poll_watcher(
+    track_path: &PathBuf,
+    tx: Sender<DebouncedEvent>,
+    poll_interval: Duration,
+) -> Result<PollWatcher, notify::Error> {
+    let mut watcher: PollWatcher = Watcher::new(tx, poll_interval)?;
+    watcher.watch(track_path, RecursiveMode::Recursive)?;
+
+    Ok(watcher)
+}
+
+/// Watches a single directory (but not its subdirectories), such as the directory containing the user configuration
+pub fn get_directory_watcher(
+    dir: &Path,
+    tx: Sender<DebouncedEvent>,
+) -> Result<RecommendedWatcher, notify::Error> {
+    let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(0))?;
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+
     Ok(watcher)
 }
+
+/// Checks if the error was caused by exhausting the operating system limit on watches (inotify on Linux)
+fn is_watch_limit_error(err: &notify::Error) -> bool {
+    match err {
+        // ENOSPC is reported when the inotify watch limit is reached
+        notify::Error::Io(err) => cfg!(target_os = "linux") && err.raw_os_error() == Some(28),
+        // newer releases of notify describe the watch limit error rather than passing on ENOSPC
+        notify::Error::Generic(message) => {
+            message.contains("limit on the total number of inotify watches")
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+    use std::sync::mpsc::channel;
+    use tempfile::tempdir;
+
+    #[test]
+    fn get_watcher_poll() {
+        let dir = tempdir().unwrap();
+        let (tx, _rx) = channel();
+
+        let watcher = get_watcher(
+            &dir.path().to_path_buf(),
+            tx,
+            &get_mock_options(WatcherBackend::Poll),
+        )
+        .unwrap();
+
+        assert_eq!(WatcherBackend::Poll, watcher.backend());
+    }
+
+    #[test]
+    fn get_watcher_missing_path() {
+        let dir = tempdir().unwrap();
+        let (tx, _rx) = channel();
+
+        assert!(get_watcher(
+            &dir.path().join("missing"),
+            tx,
+            &get_mock_options(WatcherBackend::Native),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn get_watcher_native_with_ignore_rules() {
+        let dir = tempdir().unwrap();
+        let (tx, _rx) = channel();
+
+        let watcher = get_watcher(
+            &dir.path().to_path_buf(),
+            tx,
+            &get_mock_options(WatcherBackend::Native),
+        )
+        .unwrap();
+
+        assert_eq!(WatcherBackend::Native, watcher.backend());
+        assert_eq!(
+            cfg!(target_os = "linux"),
+            match watcher {
+                TrackWatcher::Pruned(_) => true,
+                _ => false,
+            }
+        );
+    }
+
+    #[test]
+    fn watch_limit_error() {
+        assert!(is_watch_limit_error(&notify::Error::Generic(String::from(
+            "Can't watch (more) files, limit on the total number of inotify watches reached"
+        ))));
+        assert_eq!(
+            cfg!(target_os = "linux"),
+            is_watch_limit_error(&notify::Error::Io(io::Error::from_raw_os_error(28)))
+        );
+        assert!(!is_watch_limit_error(&notify::Error::PathNotFound));
+    }
+
+    fn get_mock_options(backend: WatcherBackend) -> WatcherOptions {
+        WatcherOptions {
+            backend,
+            poll_interval: Duration::from_secs(1),
+            ignore: IgnoreRules::new(&["target"]),
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use crate::config::ignore::IgnoreRules;
+use log::{debug, log};
+use notify::{DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::mpsc::Sender;
+use std::time::Duration;
+
+/// Watches each directory within a track path separately, skipping ignored directories
+///
+/// Watching a track path recursively uses one kernel watch per directory on Linux, including
+/// directories like `node_modules` which are never tracked. Walking the tree and watching each
+/// allowed directory non-recursively means ignored directories cost no watches. New directories
+/// are not watched automatically, they must be added with `watch_new_tree` when they are created.
+pub struct PrunedWatcher {
+    track_path: PathBuf,
+    ignore: IgnoreRules,
+    watcher: RecommendedWatcher,
+    watched_dirs: BTreeSet<PathBuf>,
+}
+
+impl PrunedWatcher {
+    pub fn new(
+        track_path: &Path,
+        tx: Sender<DebouncedEvent>,
+        ignore: &IgnoreRules,
+    ) -> Result<Self, notify::Error> {
+        let mut watcher = PrunedWatcher {
+            track_path: track_path.to_path_buf(),
+            ignore: ignore.clone(),
+            watcher: Watcher::new(tx, Duration::from_secs(0))?,
+            watched_dirs: BTreeSet::new(),
+        };
+        watcher.watch_tree(track_path)?;
+
+        Ok(watcher)
+    }
+
+    /// Watches the directory and all of its subdirectories which are not ignored
+    pub fn watch_tree(&mut self, dir: &Path) -> Result<(), notify::Error> {
+        self.watch_dirs(dir, None)
+    }
+
+    /// Watches a directory which was just created, returning the files already in it
+    ///
+    /// Files can be created in a new directory before it is watched, and no event is sent for
+    /// them, so they are found by scanning the directory once.
+    pub fn watch_new_tree(&mut self, dir: &Path) -> Result<Vec<PathBuf>, notify::Error> {
+        let mut files = vec![];
+        self.watch_dirs(dir, Some(&mut files))?;
+        Ok(files)
+    }
+
+    fn watch_dirs(
+        &mut self,
+        dir: &Path,
+        mut files: Option<&mut Vec<PathBuf>>,
+    ) -> Result<(), notify::Error> {
+        let is_ignored = match dir.strip_prefix(&self.track_path) {
+            Ok(relative_dir) => self.ignore.is_ignored(relative_dir),
+            Err(_) => true,
+        };
+        if is_ignored || self.watched_dirs.contains(dir) {
+            return Ok(());
+        }
+
+        match self.watcher.watch(dir, RecursiveMode::NonRecursive) {
+            Ok(()) => {
+                self.watched_dirs.insert(dir.to_path_buf());
+            }
+            // the track path itself must be watched, and running out of watches needs to be handled by the caller
+            Err(err) if dir == self.track_path || super::is_watch_limit_error(&err) => {
+                return Err(err)
+            }
+            Err(err) => {
+                // subdirectories can be removed while walking the tree, or may not be readable
+                debug!("Skipping directory {:?} ({})", dir, err);
+                return Ok(());
+            }
+        }
+
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(err) => {
+                debug!("Failed to read directory {:?} ({})", dir, err);
+                return Ok(());
+            }
+        };
+        for entry in entries.filter_map(Result::ok) {
+            // symlinked directories are not followed, the same as a recursive watch
+            let is_dir = entry
+                .file_type()
+                .map(|file_type| file_type.is_dir())
+                .unwrap_or(false);
+            if is_dir {
+                self.watch_dirs(&entry.path(), files.as_deref_mut())?;
+            } else if let Some(files) = files.as_deref_mut() {
+                files.push(entry.path());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Stops watching the directory and all of its subdirectories
+    pub fn unwatch_tree(&mut self, dir: &Path) {
+        let dirs: Vec<PathBuf> = self
+            .watched_dirs
+            .iter()
+            .filter(|watched_dir| watched_dir.starts_with(dir))
+            .cloned()
+            .collect();
+        for watched_dir in dirs {
+            // the directory may already be gone, which removes the watch
+            let _ = self.watcher.unwatch(&watched_dir);
+            self.watched_dirs.remove(&watched_dir);
+        }
+    }
+
+    pub fn watched_dirs(&self) -> &BTreeSet<PathBuf> {
+        &self.watched_dirs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::channel;
+    use tempfile::tempdir;
+
+    #[test]
+    fn ignored_directories_are_not_watched() {
+        let dir = tempdir().unwrap();
+        let track_path = dir.path().to_path_buf();
+        fs::create_dir_all(track_path.join("testProj/src")).unwrap();
+        fs::create_dir_all(track_path.join("testProj/target/debug")).unwrap();
+        let (tx, _rx) = channel();
+
+        let watcher = PrunedWatcher::new(&track_path, tx, &IgnoreRules::new(["target"])).unwrap();
+
+        assert_eq!(
+            vec![
+                track_path.clone(),
+                track_path.join("testProj"),
+                track_path.join("testProj/src"),
+            ],
+            watcher.watched_dirs().iter().cloned().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn watch_and_unwatch_new_tree() {
+        let dir = tempdir().unwrap();
+        let track_path = dir.path().to_path_buf();
+        let (tx, _rx) = channel();
+        let mut watcher =
+            PrunedWatcher::new(&track_path, tx, &IgnoreRules::new(["target"])).unwrap();
+
+        fs::create_dir_all(track_path.join("testProj/src")).unwrap();
+        fs::create_dir_all(track_path.join("testProj/target")).unwrap();
+        fs::write(track_path.join("testProj/src/main.rs"), "").unwrap();
+        fs::write(track_path.join("testProj/target/testProj"), "").unwrap();
+        assert_eq!(
+            vec![track_path.join("testProj/src/main.rs")],
+            watcher
+                .watch_new_tree(&track_path.join("testProj"))
+                .unwrap()
+        );
+        assert_eq!(3, watcher.watched_dirs().len());
+
+        watcher.unwatch_tree(&track_path.join("testProj"));
+        assert_eq!(1, watcher.watched_dirs().len());
+    }
+}
@@ -0,0 +1,301 @@
+use crate::watcher::{get_poll_watcher, get_watcher, is_watch_limit_error};
+use crate::watcher::{TrackWatcher, WatcherOptions};
+use log::{info, log, warn};
+use notify::DebouncedEvent;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::mpsc::Sender;
+
+/// The watchers for all track paths, including track paths which could not be watched yet
+///
+/// Track paths which fail to be watched (for example, a directory on an external drive which
+/// is not mounted) are retried by `retry_failed`, so they are picked up once they become available.
+pub struct WatcherSet {
+    tx: Sender<DebouncedEvent>,
+    options: WatcherOptions,
+    watchers: HashMap<PathBuf, TrackWatcher>,
+    failed: BTreeSet<PathBuf>,
+}
+
+impl WatcherSet {
+    pub fn new(tx: Sender<DebouncedEvent>, options: WatcherOptions) -> Self {
+        WatcherSet {
+            tx,
+            options,
+            watchers: HashMap::new(),
+            failed: BTreeSet::new(),
+        }
+    }
+
+    /// Starts watching the track path, or records it to be retried if it can't be watched
+    pub fn watch(&mut self, track_path: &PathBuf) {
+        match get_watcher(track_path, self.tx.clone(), &self.options) {
+            Ok(watcher) => {
+                info!(
+                    "Watching {:?} using the {} watcher",
+                    track_path,
+                    watcher.backend()
+                );
+                self.failed.remove(track_path);
+                self.watchers.insert(track_path.clone(), watcher);
+            }
+            Err(err) => {
+                if self.failed.insert(track_path.clone()) {
+                    warn!("Failed to watch {:?} ({}), will retry", track_path, err);
+                }
+            }
+        }
+    }
+
+    /// Drops the watcher for the track path and watches it again
+    ///
+    /// This re-establishes watches after the watcher reports an error or a rescan, for example
+    /// when the directory was deleted and recreated.
+    pub fn rewatch(&mut self, track_path: &PathBuf) {
+        info!("Re-establishing watch on {:?}", track_path);
+        // the old watcher must be dropped first, otherwise the native watcher may ignore the new watch
+        self.watchers.remove(track_path);
+        self.watch(track_path);
+    }
+
+    pub fn rewatch_all(&mut self) {
+        for track_path in self.track_paths() {
+            self.rewatch(&track_path);
+        }
+    }
+
+    /// Watches the given track paths, and stops watching any others
+    ///
+    /// Changing the watcher options re-establishes the watches on all track paths.
+    pub fn update(&mut self, track_paths: &[PathBuf], options: WatcherOptions) {
+        for track_path in self.track_paths() {
+            if !track_paths.contains(&track_path) {
+                info!("Stopped watching {:?}", track_path);
+                self.watchers.remove(&track_path);
+                self.failed.remove(&track_path);
+            }
+        }
+
+        if options != self.options {
+            self.options = options;
+            self.rewatch_all();
+        }
+
+        for track_path in track_paths {
+            if !self.track_paths().contains(track_path) {
+                self.watch(track_path);
+            }
+        }
+    }
+
+    /// Stops watching track paths which no longer exist, and retries track paths which failed
+    pub fn retry_failed(&mut self) {
+        let missing: Vec<PathBuf> = self
+            .watchers
+            .keys()
+            .filter(|track_path| !track_path.exists())
+            .cloned()
+            .collect();
+        for track_path in missing {
+            self.mark_failed(&track_path);
+        }
+
+        let failed: Vec<PathBuf> = self.failed.iter().cloned().collect();
+        for track_path in failed {
+            if track_path.exists() {
+                self.watch(&track_path);
+            }
+        }
+    }
+
+    /// Stops watching the track path until it can be watched again by `retry_failed`
+    pub fn mark_failed(&mut self, track_path: &PathBuf) {
+        if self.watchers.remove(track_path).is_some() {
+            warn!("Stopped watching {:?}, will retry", track_path);
+        }
+        self.failed.insert(track_path.clone());
+    }
+
+    /// Watches a directory which was created (or moved) within a track path
+    ///
+    /// This is only needed when directories are watched individually, other watchers pick up new
+    /// directories on their own. If there are no watches left, the track path falls back to polling.
+    /// Returns the files which were already in the directory when it was watched.
+    pub fn watch_new_dir(&mut self, dir: &Path) -> Vec<PathBuf> {
+        let track_path = match self.track_path_of(dir) {
+            Some(track_path) => track_path,
+            None => return vec![],
+        };
+        let result = match self.watchers.get_mut(&track_path) {
+            Some(TrackWatcher::Pruned(watcher)) => watcher.watch_new_tree(dir),
+            _ => return vec![],
+        };
+
+        match result {
+            Ok(files) => return files,
+            Err(ref err) if is_watch_limit_error(err) => {
+                warn!(
+                    "Watch limit reached for path {:?}, falling back to polling",
+                    track_path
+                );
+                match get_poll_watcher(&track_path, self.tx.clone(), self.options.poll_interval) {
+                    Ok(watcher) => {
+                        self.watchers
+                            .insert(track_path.clone(), TrackWatcher::Poll(watcher));
+                    }
+                    Err(_) => self.mark_failed(&track_path),
+                }
+            }
+            Err(err) => warn!("Failed to watch new directory {:?} ({})", dir, err),
+        }
+        vec![]
+    }
+
+    /// Stops watching a directory which was removed (or moved) within a track path
+    pub fn unwatch_dir(&mut self, dir: &Path) {
+        let track_path = match self.track_path_of(dir) {
+            Some(track_path) => track_path,
+            None => return,
+        };
+        if let Some(TrackWatcher::Pruned(watcher)) = self.watchers.get_mut(&track_path) {
+            watcher.unwatch_tree(dir);
+        }
+    }
+
+    /// Returns the track path containing the path
+    pub fn track_path_of(&self, path: &Path) -> Option<PathBuf> {
+        self.track_paths()
+            .into_iter()
+            .find(|track_path| path.starts_with(track_path))
+    }
+
+    /// All track paths, whether or not they are currently watched
+    pub fn track_paths(&self) -> Vec<PathBuf> {
+        self.watchers
+            .keys()
+            .chain(self.failed.iter())
+            .cloned()
+            .collect()
+    }
+
+    pub fn is_watching(&self, track_path: &Path) -> bool {
+        self.watchers.contains_key(track_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ignore::IgnoreRules;
+    use crate::watcher::WatcherBackend;
+    use std::fs;
+    use std::sync::mpsc::channel;
+    use std::time::Duration;
+    use tempfile::tempdir;
+
+    #[test]
+    fn retry_failed_watches_created_path() {
+        let dir = tempdir().unwrap();
+        let track_path = dir.path().join("Projects");
+        let (tx, _rx) = channel();
+        let mut watchers = WatcherSet::new(tx, get_mock_options());
+
+        watchers.watch(&track_path);
+        assert!(!watchers.is_watching(&track_path));
+
+        fs::create_dir(&track_path).unwrap();
+        watchers.retry_failed();
+        assert!(watchers.is_watching(&track_path));
+    }
+
+    #[test]
+    fn retry_failed_stops_watching_removed_path() {
+        let dir = tempdir().unwrap();
+        let track_path = dir.path().join("Projects");
+        fs::create_dir(&track_path).unwrap();
+        let (tx, _rx) = channel();
+        let mut watchers = WatcherSet::new(tx, get_mock_options());
+
+        watchers.watch(&track_path);
+        assert!(watchers.is_watching(&track_path));
+
+        fs::remove_dir(&track_path).unwrap();
+        watchers.retry_failed();
+        assert!(!watchers.is_watching(&track_path));
+        assert_eq!(vec![track_path.clone()], watchers.track_paths());
+
+        fs::create_dir(&track_path).unwrap();
+        watchers.retry_failed();
+        assert!(watchers.is_watching(&track_path));
+    }
+
+    #[test]
+    fn update_adds_and_removes_track_paths() {
+        let dir = tempdir().unwrap();
+        let track_path_1 = dir.path().join("Projects");
+        let track_path_2 = dir.path().join("OtherProjects");
+        fs::create_dir(&track_path_1).unwrap();
+        fs::create_dir(&track_path_2).unwrap();
+        let (tx, _rx) = channel();
+        let mut watchers = WatcherSet::new(tx, get_mock_options());
+        watchers.watch(&track_path_1);
+
+        watchers.update(&[track_path_2.clone()], get_mock_options());
+
+        assert!(!watchers.is_watching(&track_path_1));
+        assert!(watchers.is_watching(&track_path_2));
+        assert_eq!(vec![track_path_2], watchers.track_paths());
+    }
+
+    #[test]
+    fn track_path_of() {
+        let dir = tempdir().unwrap();
+        let track_path = dir.path().join("Projects");
+        let (tx, _rx) = channel();
+        let mut watchers = WatcherSet::new(tx, get_mock_options());
+
+        watchers.watch(&track_path);
+
+        assert_eq!(
+            Some(track_path.clone()),
+            watchers.track_path_of(&track_path.join("testProj/file1.rs"))
+        );
+        assert_eq!(None, watchers.track_path_of(Path::new("/tmp/file1.rs")));
+    }
+
+    #[test]
+    fn watch_new_dir() {
+        let dir = tempdir().unwrap();
+        let track_path = dir.path().join("Projects");
+        fs::create_dir(&track_path).unwrap();
+        let (tx, _rx) = channel();
+        let mut watchers = WatcherSet::new(tx, get_mock_options());
+        watchers.watch(&track_path);
+
+        let new_dir = track_path.join("testProj");
+        fs::create_dir(&new_dir).unwrap();
+        fs::write(new_dir.join("main.rs"), "").unwrap();
+        let files = watchers.watch_new_dir(&new_dir);
+
+        if let Some(TrackWatcher::Pruned(watcher)) = watchers.watchers.get(&track_path) {
+            assert!(watcher.watched_dirs().contains(&new_dir));
+            assert_eq!(vec![new_dir.join("main.rs")], files);
+        }
+
+        watchers.unwatch_dir(&new_dir);
+
+        if let Some(TrackWatcher::Pruned(watcher)) = watchers.watchers.get(&track_path) {
+            assert!(!watcher.watched_dirs().contains(&new_dir));
+        }
+    }
+
+    fn get_mock_options() -> WatcherOptions {
+        WatcherOptions {
+            backend: WatcherBackend::Native,
+            poll_interval: Duration::from_secs(1),
+            ignore: IgnoreRules::new(&["target"]),
+        }
+    }
+}
@@ -1,81 +1,102 @@
 use std::fs;
 use std::fs::OpenOptions;
 use std::io::Write;
+use std::path::PathBuf;
 use std::process::Child;
 use std::process::Command;
 use std::process::Stdio;
 use std::thread;
 use std::time;
 
-use timetrack::get_config;
+use tempfile::TempDir;
 
-struct Backup;
+/// A separate TimeTrack profile, so tests never touch the user's real configuration or data
+struct Profile {
+    dir: TempDir,
+}
 
-impl Backup {
+impl Profile {
     fn new() -> Self {
-        let config = get_config();
-        fs::copy(
-            &config.raw_data_path,
-            (&config.raw_data_path).clone().with_extension("bak"),
-        )
-        .unwrap();
-        fs::copy(
-            &config.processed_data_path,
-            (&config.processed_data_path).clone().with_extension("bak"),
+        let dir = tempfile::tempdir().unwrap();
+        let profile = Profile { dir };
+
+        fs::create_dir(profile.projects_dir()).unwrap();
+        fs::write(
+            profile.dir.path().join("timetrack_config"),
+            format!("track_paths = [{:?}]\n", profile.projects_dir()),
         )
         .unwrap();
 
-        Backup
+        profile
     }
-}
 
-impl Drop for Backup {
-    fn drop(&mut self) {
-        let config = get_config();
-        fs::rename(
-            (&config.raw_data_path).clone().with_extension("bak"),
-            &config.raw_data_path,
-        )
-        .unwrap();
-        fs::rename(
-            (&config.processed_data_path).clone().with_extension("bak"),
-            &config.processed_data_path,
-        )
-        .unwrap();
+    fn projects_dir(&self) -> PathBuf {
+        self.dir.path().canonicalize().unwrap().join("Projects")
+    }
+
+    fn data_dir(&self) -> PathBuf {
+        self.dir.path().join("data")
+    }
+
+    /// Creates a git repository in the tracked directory, so changes in it are tracked
+    fn create_project(&self, name: &str) -> PathBuf {
+        let project_dir = self.projects_dir().join(name);
+        fs::create_dir(&project_dir).unwrap();
+        Command::new("git")
+            .args(&["init", "-q"])
+            .current_dir(&project_dir)
+            .status()
+            .unwrap();
+        project_dir
+    }
+
+    fn command(&self) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_timetrack"));
+        command
+            .env("TIMETRACK_CONFIG", self.dir.path().join("timetrack_config"))
+            .env("TIMETRACK_DATA_DIR", self.data_dir());
+        command
     }
 }
 
-fn tracker_proc() -> Child {
-    Command::new("cargo")
-        .arg("run")
-        .arg("--")
+fn tracker_proc(profile: &Profile) -> Child {
+    profile
+        .command()
         .arg("track")
         .spawn()
         .expect("failed to execute child")
 }
 
-fn clear_proc() -> Child {
-    Command::new("cargo")
-        .arg("run")
-        .arg("--")
+fn clear_proc(profile: &Profile) -> Child {
+    profile
+        .command()
         .arg("clear")
         .spawn()
         .expect("failed to execute child")
 }
 
-fn calc_proc() -> Child {
-    Command::new("cargo")
-        .arg("run")
+fn calc_proc(profile: &Profile) -> Child {
+    profile
+        .command()
         .stdout(Stdio::piped())
         .spawn()
         .expect("failed to execute child")
 }
 
-fn clear_and_verify() {
-    let mut clear = clear_proc(); // clear logs
+/// Waits for the tracker to create its lock file, and then for the watchers to start
+fn wait_for_tracker(profile: &Profile) {
+    let lock_file = profile.data_dir().join(".timetrack_lock");
+    while !lock_file.exists() {
+        thread::sleep(time::Duration::from_millis(100));
+    }
+    thread::sleep(time::Duration::from_millis(1000));
+}
+
+fn clear_and_verify(profile: &Profile) {
+    let mut clear = clear_proc(profile); // clear logs
     clear.wait().unwrap();
 
-    let calc = calc_proc();
+    let calc = calc_proc(profile);
 
     let output = calc.wait_with_output().expect("failed to wait on child");
 
@@ -84,13 +105,10 @@ fn clear_and_verify() {
     assert_eq!("No time track data found\n", output_text); // ensure logs have been cleared
 }
 
-fn create_filesystem_noise() {
-    let test_file_path = get_config()
-        .track_paths
-        .get(0)
-        .unwrap()
-        .to_owned()
-        .join("timetrack/__integration_test__");
+fn create_filesystem_noise(profile: &Profile) {
+    let test_file_path = profile
+        .create_project("timetrack")
+        .join("__integration_test__");
 
     {
         let mut file = OpenOptions::new()
@@ -130,18 +148,19 @@ fn create_filesystem_noise() {
 #[test]
 #[ignore]
 fn integration() {
-    // Ensure there are no instances of TimeTrack running while this test is running
-    let _backup = Backup::new(); // backup implements drop to ensure backup file is restored after test completes
+    let profile = Profile::new();
 
-    clear_and_verify();
+    clear_and_verify(&profile);
 
-    let mut tracker = tracker_proc(); // start watching file system
+    let mut tracker = tracker_proc(&profile); // start watching file system
+    wait_for_tracker(&profile);
 
-    create_filesystem_noise();
+    create_filesystem_noise(&profile);
 
     tracker.kill().expect("command wasn't running");
+    tracker.wait().unwrap();
 
-    let calc = calc_proc();
+    let calc = calc_proc(&profile);
 
     let output = calc.wait_with_output().expect("failed to wait on child");
 
@@ -149,5 +168,31 @@ fn integration() {
 
     assert!(output_text.contains("timetrack"));
 
-    clear_and_verify();
+    clear_and_verify(&profile);
+}
+
+#[cfg(unix)]
+#[test]
+fn signal_flushes_pending_events() {
+    let profile = Profile::new();
+    let project_dir = profile.create_project("signalProj");
+
+    let mut tracker = tracker_proc(&profile);
+    wait_for_tracker(&profile);
+
+    fs::write(project_dir.join("main.rs"), "fn main() {}").unwrap();
+    // well within the batch window, so the event is still pending when the signal arrives
+    thread::sleep(time::Duration::from_millis(500));
+    Command::new("kill")
+        .args(&["-TERM", &tracker.id().to_string()])
+        .status()
+        .unwrap();
+
+    assert!(tracker.wait().unwrap().success());
+    assert!(
+        fs::read_to_string(profile.data_dir().join(".timetrack_raw"))
+            .unwrap()
+            .contains("signalProj")
+    );
+    assert!(!profile.data_dir().join(".timetrack_control").exists());
 }